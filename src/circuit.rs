@@ -0,0 +1,235 @@
+use std::ops::{Add, AddAssign, Bound, Index, Mul, RangeBounds};
+use std::slice::SliceIndex;
+
+use crate::gates::{is_fusable, is_noise};
+use crate::ir::StimInstr;
+use crate::normalize::normalize;
+use crate::parser::parse_lines;
+use crate::stats::{CircuitStats, stats};
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Circuit {
+    pub instrs: Vec<StimInstr>,
+}
+
+impl Circuit {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_instrs(instrs: Vec<StimInstr>) -> Self {
+        Self { instrs }
+    }
+
+    pub fn parse(input: &str) -> Result<Self, String> {
+        Ok(Self::from_instrs(parse_lines(input)?))
+    }
+
+    pub fn len(&self) -> usize {
+        self.instrs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.instrs.is_empty()
+    }
+
+    pub fn stats(&self) -> CircuitStats {
+        stats(&self.instrs)
+    }
+
+    /// Copies a range of top-level instructions into a new circuit.
+    pub fn slice(&self, range: impl RangeBounds<usize>) -> Circuit {
+        let start = match range.start_bound() {
+            Bound::Included(&s) => s,
+            Bound::Excluded(&s) => s + 1,
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(&e) => e + 1,
+            Bound::Excluded(&e) => e,
+            Bound::Unbounded => self.instrs.len(),
+        };
+        Self::from_instrs(self.instrs[start..end].to_vec())
+    }
+
+    /// Structural equality where instruction arguments may differ by up to `atol`.
+    pub fn approx_eq(&self, other: &Circuit, atol: f64) -> bool {
+        instrs_approx_eq(&self.instrs, &other.instrs, atol)
+    }
+
+    /// Returns a copy with compatible instructions fused; see [`normalize`].
+    pub fn normalized(&self) -> Circuit {
+        Self::from_instrs(normalize(self.instrs.clone()))
+    }
+
+    /// Returns a copy with every noise channel removed.
+    pub fn without_noise(&self) -> Circuit {
+        Self::from_instrs(strip_noise(&self.instrs))
+    }
+
+    /// Appends an instruction, fusing it into the last one when compatible.
+    pub fn push(&mut self, instr: StimInstr) {
+        if let Some(last) = self.instrs.last_mut()
+            && try_fuse(last, &instr)
+        {
+            return;
+        }
+        self.instrs.push(instr);
+    }
+}
+
+impl<I: SliceIndex<[StimInstr]>> Index<I> for Circuit {
+    type Output = I::Output;
+
+    fn index(&self, index: I) -> &Self::Output {
+        &self.instrs[index]
+    }
+}
+
+impl AddAssign<&Circuit> for Circuit {
+    fn add_assign(&mut self, rhs: &Circuit) {
+        let mut rest = rhs.instrs.iter();
+        if let Some(first) = rest.next() {
+            self.push(first.clone());
+        }
+        self.instrs.extend(rest.cloned());
+    }
+}
+
+impl AddAssign for Circuit {
+    fn add_assign(&mut self, rhs: Circuit) {
+        *self += &rhs;
+    }
+}
+
+impl Add<&Circuit> for &Circuit {
+    type Output = Circuit;
+
+    fn add(self, rhs: &Circuit) -> Circuit {
+        let mut out = self.clone();
+        out += rhs;
+        out
+    }
+}
+
+impl Add for Circuit {
+    type Output = Circuit;
+
+    fn add(mut self, rhs: Circuit) -> Circuit {
+        self += &rhs;
+        self
+    }
+}
+
+impl Mul<u64> for &Circuit {
+    type Output = Circuit;
+
+    /// Wraps the circuit in a `REPEAT` block. Repeating zero times gives an
+    /// empty circuit and repeating once gives the circuit unchanged.
+    fn mul(self, count: u64) -> Circuit {
+        match count {
+            0 => Circuit::new(),
+            1 => self.clone(),
+            _ => Circuit::from_instrs(vec![StimInstr::Repeat {
+                count,
+                body: self.instrs.clone(),
+            }]),
+        }
+    }
+}
+
+impl Mul<u64> for Circuit {
+    type Output = Circuit;
+
+    fn mul(self, count: u64) -> Circuit {
+        &self * count
+    }
+}
+
+/// Whether `a` and `b` are the same fusable gate with identical tag and arguments.
+pub(crate) fn can_fuse(a: &StimInstr, b: &StimInstr) -> bool {
+    match (a, b) {
+        (
+            StimInstr::Op {
+                name: n1,
+                tag: t1,
+                args: a1,
+                ..
+            },
+            StimInstr::Op {
+                name: n2,
+                tag: t2,
+                args: a2,
+                ..
+            },
+        ) => n1 == n2 && t1 == t2 && a1 == a2 && is_fusable(n1),
+        _ => false,
+    }
+}
+
+/// Merges `b` into `a` when [`can_fuse`] allows it. Returns whether the
+/// fusion happened.
+pub(crate) fn try_fuse(a: &mut StimInstr, b: &StimInstr) -> bool {
+    if !can_fuse(a, b) {
+        return false;
+    }
+    if let (StimInstr::Op { targets: ts1, .. }, StimInstr::Op { targets: ts2, .. }) = (a, b) {
+        ts1.extend(ts2.iter().cloned());
+    }
+    true
+}
+
+fn strip_noise(instrs: &[StimInstr]) -> Vec<StimInstr> {
+    let mut out = Vec::new();
+    for instr in instrs {
+        match instr {
+            StimInstr::Op { name, .. } if is_noise(name) => {}
+            StimInstr::Op { .. } => out.push(instr.clone()),
+            StimInstr::Repeat { count, body } => out.push(StimInstr::Repeat {
+                count: *count,
+                body: strip_noise(body),
+            }),
+        }
+    }
+    out
+}
+
+fn instrs_approx_eq(a: &[StimInstr], b: &[StimInstr], atol: f64) -> bool {
+    a.len() == b.len() && a.iter().zip(b).all(|(x, y)| instr_approx_eq(x, y, atol))
+}
+
+fn instr_approx_eq(a: &StimInstr, b: &StimInstr, atol: f64) -> bool {
+    match (a, b) {
+        (
+            StimInstr::Op {
+                name: n1,
+                tag: t1,
+                args: a1,
+                targets: ts1,
+            },
+            StimInstr::Op {
+                name: n2,
+                tag: t2,
+                args: a2,
+                targets: ts2,
+            },
+        ) => {
+            n1 == n2
+                && t1 == t2
+                && ts1 == ts2
+                && a1.len() == a2.len()
+                && a1.iter().zip(a2).all(|(x, y)| (x - y).abs() <= atol)
+        }
+        (
+            StimInstr::Repeat {
+                count: c1,
+                body: b1,
+            },
+            StimInstr::Repeat {
+                count: c2,
+                body: b2,
+            },
+        ) => c1 == c2 && instrs_approx_eq(b1, b2, atol),
+        _ => false,
+    }
+}
@@ -0,0 +1,361 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+
+use crate::dem::DetectorErrorModel;
+use crate::dem_sampler::DemSampler;
+use crate::error_analyzer::{self, ErrorAnalyzerOptions};
+use crate::ir::StimInstr;
+use crate::m2d::M2dConverter;
+use crate::parser::StreamParser;
+use crate::result_format::{RecordLayout, ResultFormat, ResultReader, ResultWriter};
+use crate::sampler::Sampler;
+
+const USAGE: &str = "usage: rstim <command> [--flag value ...]
+
+With no command, prints the version.
+
+commands:
+  sample   --shots N --seed S --out_format FORMAT --in FILE --out FILE
+  detect   --shots N --seed S --out_format FORMAT --in FILE --out FILE
+           [--append_observables] [--obs_out FILE --obs_out_format FORMAT]
+  analyze_errors --in FILE --out FILE [--decompose_errors] [--fold_loops]
+           [--approximate_disjoint_errors] [--ignore_decomposition_failures]
+  m2d      --circuit FILE --in FILE --in_format FORMAT --out FILE --out_format FORMAT
+           [--sweep FILE --sweep_format FORMAT]
+           [--append_observables] [--obs_out FILE --obs_out_format FORMAT]
+  sample_dem --shots N --seed S --in FILE --out FILE --out_format FORMAT
+           [--append_observables] [--obs_out FILE --obs_out_format FORMAT]
+
+FORMAT is one of 01, b8, r8, hits, dets, ptb64.";
+
+/// Runs the command line interface on `args` (excluding the program name).
+pub fn run(args: &[String]) -> Result<(), String> {
+    let Some((command, rest)) = args.split_first() else {
+        println!("rstim {}", crate::version());
+        return Ok(());
+    };
+    match command.as_str() {
+        "sample" => sample(Flags::parse(rest)?),
+        "detect" => detect(Flags::parse(rest)?),
+        "analyze_errors" => analyze_errors(Flags::parse(rest)?),
+        "m2d" => m2d(Flags::parse(rest)?),
+        "sample_dem" => sample_dem(Flags::parse(rest)?),
+        "--version" | "version" => {
+            println!("rstim {}", crate::version());
+            Ok(())
+        }
+        "--help" | "help" => {
+            println!("{USAGE}");
+            Ok(())
+        }
+        _ => Err(format!("unknown command {command}\n{USAGE}")),
+    }
+}
+
+fn sample(mut flags: Flags) -> Result<(), String> {
+    let shots: u64 = flags.parsed("shots")?.unwrap_or(1);
+    let seed: Option<u64> = flags.parsed("seed")?;
+    let format: ResultFormat = flags
+        .parsed("out_format")?
+        .unwrap_or(ResultFormat::Format01);
+    let input = flags.take("in")?;
+    let output = flags.take("out")?;
+    flags.finish()?;
+
+    let instrs = read_circuit(input.as_deref())?;
+    let mut sampler = Sampler::new(instrs, resolve_seed(seed))?;
+    sampler.set_threads(std::thread::available_parallelism().map_or(1, |n| n.get()));
+    let layout = RecordLayout::measurements(sampler.num_measurements());
+    let mut writer = ResultWriter::new(open_output(output.as_deref())?, format, layout);
+    let mut first = 0;
+    while first < shots {
+        let batch = (shots - first).min(SAMPLE_BATCH);
+        let table = sampler.sample_range(first, batch as usize)?;
+        for row in 0..table.num_rows() {
+            writer
+                .write_shot(&table.row(row))
+                .map_err(|e| e.to_string())?;
+        }
+        first += batch;
+    }
+    writer.finish().map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Shots sampled per `Sampler` or `DemSampler` call, bounding memory use.
+const SAMPLE_BATCH: u64 = 4096;
+
+fn detect(mut flags: Flags) -> Result<(), String> {
+    let shots: u64 = flags.parsed("shots")?.unwrap_or(1);
+    let seed: Option<u64> = flags.parsed("seed")?;
+    let dest = DetectionFlags::take(&mut flags)?;
+    let input = flags.take("in")?;
+    flags.finish()?;
+
+    let instrs = read_circuit(input.as_deref())?;
+    let mut sampler = Sampler::new(instrs, resolve_seed(seed))?;
+    sampler.set_threads(std::thread::available_parallelism().map_or(1, |n| n.get()));
+    let mut sink = dest.open(sampler.num_detectors(), sampler.num_observables())?;
+    let mut first = 0;
+    while first < shots {
+        let batch = (shots - first).min(SAMPLE_BATCH);
+        let (detectors, observables) = sampler.detect_range(first, batch as usize)?;
+        for row in 0..detectors.num_rows() {
+            sink.write(detectors.row(row), &observables.row(row))?;
+        }
+        first += batch;
+    }
+    sink.finish()
+}
+
+fn m2d(mut flags: Flags) -> Result<(), String> {
+    let circuit = flags.take("circuit")?.ok_or("--circuit is required")?;
+    let input = flags.take("in")?;
+    let in_format: ResultFormat = flags.parsed("in_format")?.unwrap_or(ResultFormat::Format01);
+    let sweep = flags.take("sweep")?;
+    let sweep_format: ResultFormat = flags
+        .parsed("sweep_format")?
+        .unwrap_or(ResultFormat::Format01);
+    let dest = DetectionFlags::take(&mut flags)?;
+    flags.finish()?;
+
+    let mut conv = M2dConverter::new(&read_circuit(Some(&circuit))?)?;
+    let mut sink = dest.open(conv.num_detectors(), conv.num_observables())?;
+    let mut measurements = ResultReader::new(
+        open_input(input.as_deref())?,
+        in_format,
+        RecordLayout::measurements(conv.num_measurements()),
+    );
+    let mut sweeps = match sweep.as_deref() {
+        Some(p) => Some(ResultReader::new(
+            open_input(Some(p))?,
+            sweep_format,
+            RecordLayout::measurements(conv.num_sweep_bits()),
+        )),
+        None => None,
+    };
+    while let Some(shot) = measurements.read_shot()? {
+        let sweep_bits = match sweeps.as_mut() {
+            Some(r) => r
+                .read_shot()?
+                .ok_or("sweep data has fewer shots than measurements")?,
+            None => Vec::new(),
+        };
+        let (detectors, observables) = conv.convert(&shot, &sweep_bits)?;
+        sink.write(detectors, &observables)?;
+    }
+    sink.finish()
+}
+
+fn analyze_errors(mut flags: Flags) -> Result<(), String> {
+    let opts = ErrorAnalyzerOptions {
+        decompose_errors: flags.switch("decompose_errors")?,
+        fold_loops: flags.switch("fold_loops")?,
+        approximate_disjoint_errors: flags.switch("approximate_disjoint_errors")?,
+        ignore_decomposition_failures: flags.switch("ignore_decomposition_failures")?,
+    };
+    let input = flags.take("in")?;
+    let output = flags.take("out")?;
+    flags.finish()?;
+
+    let instrs = read_circuit(input.as_deref())?;
+    let model = error_analyzer::analyze_errors(&instrs, &opts)?;
+    let mut out = open_output(output.as_deref())?;
+    write!(out, "{model}")
+        .and_then(|_| out.flush())
+        .map_err(|e| e.to_string())
+}
+
+fn sample_dem(mut flags: Flags) -> Result<(), String> {
+    let shots: u64 = flags.parsed("shots")?.unwrap_or(1);
+    let seed: Option<u64> = flags.parsed("seed")?;
+    let dest = DetectionFlags::take(&mut flags)?;
+    let input = flags.take("in")?;
+    flags.finish()?;
+
+    let model = DetectorErrorModel::from_reader(open_input(input.as_deref())?)?;
+    let sampler = DemSampler::new(&model);
+    let mut sink = dest.open(sampler.num_detectors(), sampler.num_observables())?;
+    let seed = resolve_seed(seed);
+    let mut first = 0;
+    while first < shots {
+        let batch = (shots - first).min(SAMPLE_BATCH);
+        for shot in sampler.sample_range(seed, first, batch as usize) {
+            sink.write(shot.detectors, &shot.observables)?;
+        }
+        first += batch;
+    }
+    sink.finish()
+}
+
+fn read_circuit(path: Option<&str>) -> Result<Vec<StimInstr>, String> {
+    StreamParser::new(open_input(path)?).collect()
+}
+
+fn open_input(path: Option<&str>) -> Result<Box<dyn BufRead>, String> {
+    Ok(match path {
+        Some(p) => Box::new(BufReader::new(
+            File::open(p).map_err(|e| format!("{p}: {e}"))?,
+        )),
+        None => Box::new(io::stdin().lock()),
+    })
+}
+
+fn open_output(path: Option<&str>) -> Result<Box<dyn Write>, String> {
+    Ok(match path {
+        Some(p) => Box::new(BufWriter::new(
+            File::create(p).map_err(|e| format!("{p}: {e}"))?,
+        )),
+        None => Box::new(BufWriter::new(io::stdout().lock())),
+    })
+}
+
+/// Uses the given seed, or a fresh random one when none was requested.
+fn resolve_seed(seed: Option<u64>) -> u64 {
+    seed.unwrap_or_else(rand::random)
+}
+
+/// Output flags shared by commands producing detection events.
+struct DetectionFlags {
+    output: Option<String>,
+    format: ResultFormat,
+    append_observables: bool,
+    obs_out: Option<String>,
+    obs_format: ResultFormat,
+}
+
+impl DetectionFlags {
+    fn take(flags: &mut Flags) -> Result<Self, String> {
+        Ok(Self {
+            output: flags.take("out")?,
+            format: flags
+                .parsed("out_format")?
+                .unwrap_or(ResultFormat::Format01),
+            append_observables: flags.switch("append_observables")?,
+            obs_out: flags.take("obs_out")?,
+            obs_format: flags
+                .parsed("obs_out_format")?
+                .unwrap_or(ResultFormat::Format01),
+        })
+    }
+
+    fn open(self, num_detectors: usize, num_observables: usize) -> Result<DetectionSink, String> {
+        let appended = if self.append_observables {
+            num_observables
+        } else {
+            0
+        };
+        let writer = ResultWriter::new(
+            open_output(self.output.as_deref())?,
+            self.format,
+            RecordLayout::detectors(num_detectors, appended),
+        );
+        let obs_writer = match self.obs_out.as_deref() {
+            Some(p) => Some(ResultWriter::new(
+                open_output(Some(p))?,
+                self.obs_format,
+                RecordLayout::detectors(0, num_observables),
+            )),
+            None => None,
+        };
+        Ok(DetectionSink {
+            writer,
+            obs_writer,
+            append_observables: self.append_observables,
+        })
+    }
+}
+
+struct DetectionSink {
+    writer: ResultWriter<Box<dyn Write>>,
+    obs_writer: Option<ResultWriter<Box<dyn Write>>>,
+    append_observables: bool,
+}
+
+impl DetectionSink {
+    fn write(&mut self, mut detectors: Vec<bool>, observables: &[bool]) -> Result<(), String> {
+        if self.append_observables {
+            detectors.extend_from_slice(observables);
+        }
+        self.writer
+            .write_shot(&detectors)
+            .map_err(|e| e.to_string())?;
+        if let Some(w) = self.obs_writer.as_mut() {
+            w.write_shot(observables).map_err(|e| e.to_string())?;
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> Result<(), String> {
+        self.writer.finish().map_err(|e| e.to_string())?;
+        if let Some(w) = self.obs_writer {
+            w.finish().map_err(|e| e.to_string())?;
+        }
+        Ok(())
+    }
+}
+
+/// `--name value`, `--name=value` and bare `--switch` flags. Dashes and
+/// underscores in flag names are interchangeable.
+struct Flags {
+    values: HashMap<String, Option<String>>,
+}
+
+impl Flags {
+    fn parse(args: &[String]) -> Result<Self, String> {
+        let mut values = HashMap::new();
+        let mut it = args.iter().peekable();
+        while let Some(arg) = it.next() {
+            let name = arg
+                .strip_prefix("--")
+                .ok_or_else(|| format!("unexpected argument {arg}"))?;
+            let (name, value) = match name.split_once('=') {
+                Some((n, v)) => (n, Some(v.to_string())),
+                None => match it.peek() {
+                    Some(next) if !next.starts_with("--") => (name, it.next().cloned()),
+                    _ => (name, None),
+                },
+            };
+            let name = name.replace('-', "_");
+            if values.insert(name.clone(), value).is_some() {
+                return Err(format!("duplicate flag --{name}"));
+            }
+        }
+        Ok(Self { values })
+    }
+
+    fn take(&mut self, name: &str) -> Result<Option<String>, String> {
+        match self.values.remove(name) {
+            None => Ok(None),
+            Some(None) => Err(format!("--{name} needs a value")),
+            Some(value) => Ok(value),
+        }
+    }
+
+    fn switch(&mut self, name: &str) -> Result<bool, String> {
+        match self.values.remove(name) {
+            None => Ok(false),
+            Some(None) => Ok(true),
+            Some(Some(v)) => Err(format!("--{name} takes no value, got {v}")),
+        }
+    }
+
+    fn parsed<T: std::str::FromStr>(&mut self, name: &str) -> Result<Option<T>, String> {
+        match self.values.remove(name) {
+            None => Ok(None),
+            Some(None) => Err(format!("--{name} needs a value")),
+            Some(Some(v)) => v
+                .parse()
+                .map(Some)
+                .map_err(|_| format!("bad value for --{name}: {v}")),
+        }
+    }
+
+    fn finish(self) -> Result<(), String> {
+        match self.values.keys().next() {
+            Some(name) => Err(format!("unknown flag --{name}")),
+            None => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,393 @@
+use crate::circuit::Circuit;
+use crate::compiled::{CompiledCircuit, LoopStack, Op};
+use crate::executor::Control;
+use crate::ir::{StimInstr, StimTarget};
+use crate::pauli::{Pauli, PauliString};
+
+/// A Clifford unitary `U` on `n` qubits, stored as the images `U X_k U†`
+/// and `U Z_k U†` of every single-qubit generator.
+///
+/// Two tableaus are equal exactly when they have the same number of qubits
+/// and their unitaries agree up to global phase, so comparing
+/// `Tableau::from_circuit` of two unitary circuits on the same register
+/// checks them for equivalence.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Tableau {
+    xs: Vec<PauliString>,
+    zs: Vec<PauliString>,
+}
+
+impl Tableau {
+    pub fn identity(n: usize) -> Self {
+        let generator = |k: usize, p: Pauli| {
+            let mut s = PauliString::new(n);
+            s.set(k, p);
+            s
+        };
+        Self {
+            xs: (0..n).map(|k| generator(k, Pauli::X)).collect(),
+            zs: (0..n).map(|k| generator(k, Pauli::Z)).collect(),
+        }
+    }
+
+    /// Builds a tableau from the images of `X_k` and `Z_k`, checking that
+    /// they are Hermitian and keep the Pauli commutation relations.
+    pub fn from_outputs(xs: Vec<PauliString>, zs: Vec<PauliString>) -> Result<Self, String> {
+        let n = xs.len();
+        if zs.len() != n {
+            return Err(format!("{n} X outputs but {} Z outputs", zs.len()));
+        }
+        if let Some(p) = xs.iter().chain(&zs).find(|p| p.len() != n) {
+            return Err(format!(
+                "outputs of a {n}-qubit tableau need {n} qubits, got {p}"
+            ));
+        }
+        if let Some(p) = xs.iter().chain(&zs).find(|p| !p.is_hermitian()) {
+            return Err(format!("output {p} is not Hermitian"));
+        }
+        let all: Vec<&PauliString> = xs.iter().chain(&zs).collect();
+        for (i, a) in all.iter().enumerate() {
+            for (j, b) in all.iter().enumerate().skip(i + 1) {
+                // Only X_k and Z_k anticommute, and they sit `n` apart.
+                if a.commutes(b) != (j != i + n) {
+                    return Err(format!(
+                        "outputs {a} and {b} break the commutation relations"
+                    ));
+                }
+            }
+        }
+        Ok(Self { xs, zs })
+    }
+
+    /// The tableau of a named unitary gate (`H`, `S`, `S_DAG`, `X`, `Y`, `Z`,
+    /// `CX`/`CNOT`, `CZ` or `I`).
+    pub fn gate(name: &str) -> Result<Self, String> {
+        let (n, f): (usize, fn(&mut PauliString)) = match name {
+            "I" => (1, |_| {}),
+            "H" => (1, |p| p.h(0)),
+            "S" => (1, |p| p.s(0)),
+            "S_DAG" => (1, |p| p.s_dag(0)),
+            "X" => (1, |p| p.x_gate(0)),
+            "Y" => (1, |p| p.y_gate(0)),
+            "Z" => (1, |p| p.z_gate(0)),
+            "CX" | "CNOT" => (2, |p| p.cx(0, 1)),
+            "CZ" => (2, |p| p.cz(0, 1)),
+            _ => return Err(format!("no tableau for gate {name}")),
+        };
+        let mut t = Self::identity(n);
+        t.outputs_mut().for_each(f);
+        Ok(t)
+    }
+
+    /// The tableau of a circuit made only of unitary gates, acting on
+    /// `num_qubits` qubits; qubits the circuit never touches get the
+    /// identity. Annotations such as `TICK` and `QUBIT_COORDS` are ignored;
+    /// measurements, noise, sweep-controlled gates and targets past the
+    /// register are rejected.
+    pub fn from_circuit(circuit: &Circuit, num_qubits: usize) -> Result<Self, String> {
+        let program = CompiledCircuit::compile(&circuit.instrs)?;
+        if program.num_qubits() > num_qubits {
+            return Err(format!(
+                "circuit acts on {} qubits, more than {num_qubits}",
+                program.num_qubits()
+            ));
+        }
+        let ops = program.ops();
+        let mut t = Self::identity(num_qubits);
+        let mut loops = LoopStack::default();
+        let mut pc = 0;
+        while pc < ops.len() {
+            if let Some(next) = loops.jump(ops, pc) {
+                pc = next;
+                continue;
+            }
+            match &ops[pc] {
+                Op::H(qs) => qs.iter().for_each(|&q| t.append_gate(Gate::H(q))),
+                Op::S(qs) => qs.iter().for_each(|&q| t.append_gate(Gate::S(q))),
+                Op::SDag(qs) => qs.iter().for_each(|&q| t.append_gate(Gate::SDag(q))),
+                Op::X(qs) => qs.iter().for_each(|&q| t.append_gate(Gate::X(q))),
+                Op::Y(qs) => qs.iter().for_each(|&q| {
+                    t.append_gate(Gate::X(q));
+                    t.append_gate(Gate::Z(q));
+                }),
+                Op::Z(qs) => qs.iter().for_each(|&q| t.append_gate(Gate::Z(q))),
+                Op::Cx(pairs) | Op::Cz(pairs) => {
+                    let cz = matches!(ops[pc], Op::Cz(_));
+                    for &(c, q) in pairs {
+                        let Control::Qubit(c) = c else {
+                            return Err("sweep-controlled gates are not unitary".to_string());
+                        };
+                        if cz {
+                            t.append_gate(Gate::H(q));
+                            t.append_gate(Gate::Cx(c, q));
+                            t.append_gate(Gate::H(q));
+                        } else {
+                            t.append_gate(Gate::Cx(c, q));
+                        }
+                    }
+                }
+                Op::QubitCoords { .. } | Op::ShiftCoords(_) | Op::Tick => {}
+                op => return Err(format!("{op:?} is not a unitary operation")),
+            }
+            pc += 1;
+        }
+        Ok(t)
+    }
+
+    pub fn num_qubits(&self) -> usize {
+        self.xs.len()
+    }
+
+    /// `U X_k U†`.
+    pub fn x_output(&self, k: usize) -> &PauliString {
+        &self.xs[k]
+    }
+
+    /// `U Z_k U†`.
+    pub fn z_output(&self, k: usize) -> &PauliString {
+        &self.zs[k]
+    }
+
+    /// Conjugates a Pauli string by the unitary, `P -> U P U†`.
+    pub fn apply(&self, p: &PauliString) -> PauliString {
+        let n = self.num_qubits();
+        assert!(p.len() <= n, "{p} is longer than the {n}-qubit tableau");
+        let mut out = PauliString::new(n);
+        let mut phase = p.phase();
+        for (q, pauli) in p.iter().enumerate() {
+            match pauli {
+                Pauli::I => {}
+                Pauli::X => out *= &self.xs[q],
+                Pauli::Z => out *= &self.zs[q],
+                Pauli::Y => {
+                    // Y = i X Z.
+                    out *= &self.xs[q];
+                    out *= &self.zs[q];
+                    phase += 1;
+                }
+            }
+        }
+        out.set_phase(out.phase() + phase);
+        out
+    }
+
+    /// The unitary that applies `self` and then `second`.
+    pub fn then(&self, second: &Tableau) -> Tableau {
+        assert_eq!(
+            self.num_qubits(),
+            second.num_qubits(),
+            "composed tableaus must have the same number of qubits"
+        );
+        Tableau {
+            xs: self.xs.iter().map(|p| second.apply(p)).collect(),
+            zs: self.zs.iter().map(|p| second.apply(p)).collect(),
+        }
+    }
+
+    pub fn inverse(&self) -> Tableau {
+        // Eliminating `U` to the identity by appending G_1..G_m shows that
+        // U† = G_m...G_1, i.e. those gates applied in order.
+        let mut t = Self::identity(self.num_qubits());
+        for gate in self.eliminate() {
+            t.append_gate(gate);
+        }
+        t
+    }
+
+    /// `self ⊗ other`, with `other` acting on the qubits after `self`'s.
+    pub fn tensor(&self, other: &Tableau) -> Tableau {
+        let n = self.num_qubits() + other.num_qubits();
+        let offset = self.num_qubits();
+        let ours = |p: &PauliString| place(p, n, &(0..offset).collect::<Vec<_>>());
+        let theirs = |p: &PauliString| place(p, n, &(offset..n).collect::<Vec<_>>());
+        Tableau {
+            xs: self
+                .xs
+                .iter()
+                .map(ours)
+                .chain(other.xs.iter().map(theirs))
+                .collect(),
+            zs: self
+                .zs
+                .iter()
+                .map(ours)
+                .chain(other.zs.iter().map(theirs))
+                .collect(),
+        }
+    }
+
+    /// Applies `gate` to `targets` after this unitary.
+    pub fn append(&mut self, gate: &Tableau, targets: &[usize]) {
+        self.check_targets(gate, targets);
+        for p in self.outputs_mut() {
+            let mut sub = PauliString::new(targets.len());
+            for (j, &q) in targets.iter().enumerate() {
+                sub.set(j, p.get(q));
+            }
+            let image = gate.apply(&sub);
+            for (j, &q) in targets.iter().enumerate() {
+                p.set(q, image.get(j));
+            }
+            p.set_phase(p.phase() + image.phase());
+        }
+    }
+
+    /// Applies `gate` to `targets` before this unitary.
+    pub fn prepend(&mut self, gate: &Tableau, targets: &[usize]) {
+        self.check_targets(gate, targets);
+        let n = self.num_qubits();
+        let xs: Vec<_> = gate
+            .xs
+            .iter()
+            .map(|p| self.apply(&place(p, n, targets)))
+            .collect();
+        let zs: Vec<_> = gate
+            .zs
+            .iter()
+            .map(|p| self.apply(&place(p, n, targets)))
+            .collect();
+        for ((&q, x), z) in targets.iter().zip(xs).zip(zs) {
+            self.xs[q] = x;
+            self.zs[q] = z;
+        }
+    }
+
+    /// Synthesizes an equivalent circuit of `H`, `S_DAG`, `CX`, `X` and `Z`
+    /// gates by Gaussian elimination. It may leave trailing qubits idle, so
+    /// rebuild it with `from_circuit(&c, self.num_qubits())`.
+    pub fn to_circuit(&self) -> Circuit {
+        // Appending G_1..G_m reduces U to the identity, so U = G_1†...G_m†:
+        // the inverses run in reverse order.
+        let mut circuit = Circuit::new();
+        let q = |q: usize| StimTarget::Qubit(q as u32);
+        for gate in self.eliminate().into_iter().rev() {
+            let (name, targets) = match gate {
+                Gate::H(a) => ("H", vec![q(a)]),
+                Gate::S(a) => ("S_DAG", vec![q(a)]),
+                Gate::SDag(a) => ("S", vec![q(a)]),
+                Gate::X(a) => ("X", vec![q(a)]),
+                Gate::Z(a) => ("Z", vec![q(a)]),
+                Gate::Cx(c, t) => ("CX", vec![q(c), q(t)]),
+            };
+            circuit.push(StimInstr::new(name, vec![], targets));
+        }
+        circuit
+    }
+
+    /// Gates that, appended in order, turn this tableau into the identity.
+    fn eliminate(&self) -> Vec<Gate> {
+        let n = self.num_qubits();
+        let mut t = self.clone();
+        let mut gates = Vec::new();
+        let mut apply = |t: &mut Tableau, gate: Gate| {
+            t.append_gate(gate);
+            gates.push(gate);
+        };
+        // Once qubit k is done its outputs are ±X_k and ±Z_k, so every later
+        // output commutes with both and is the identity on qubits <= k.
+        for k in 0..n {
+            let j = (k..n)
+                .find(|&j| t.xs[k].get(j) != Pauli::I)
+                .expect("tableau outputs break the commutation relations");
+            // Make X_k's image a single X on qubit k.
+            match t.xs[k].get(j) {
+                Pauli::Z => apply(&mut t, Gate::H(j)),
+                Pauli::Y => apply(&mut t, Gate::S(j)),
+                _ => {}
+            }
+            if j != k {
+                apply(&mut t, Gate::Cx(j, k));
+                apply(&mut t, Gate::Cx(k, j));
+                apply(&mut t, Gate::Cx(j, k));
+            }
+            for m in k + 1..n {
+                match t.xs[k].get(m) {
+                    Pauli::Z => apply(&mut t, Gate::H(m)),
+                    Pauli::Y => apply(&mut t, Gate::S(m)),
+                    _ => {}
+                }
+                if t.xs[k].get(m) == Pauli::X {
+                    apply(&mut t, Gate::Cx(k, m));
+                }
+            }
+            // Z_k's image anticommutes with X_k: fold its other qubits onto k
+            // as Z's, which leaves X_k alone, then turn a Y on k into Z.
+            for m in k + 1..n {
+                match t.zs[k].get(m) {
+                    Pauli::X => apply(&mut t, Gate::H(m)),
+                    Pauli::Y => {
+                        apply(&mut t, Gate::S(m));
+                        apply(&mut t, Gate::H(m));
+                    }
+                    _ => {}
+                }
+                if t.zs[k].get(m) == Pauli::Z {
+                    apply(&mut t, Gate::Cx(m, k));
+                }
+            }
+            if t.zs[k].get(k) == Pauli::Y {
+                apply(&mut t, Gate::H(k));
+                apply(&mut t, Gate::S(k));
+                apply(&mut t, Gate::H(k));
+            }
+            if t.xs[k].is_negative() {
+                apply(&mut t, Gate::Z(k));
+            }
+            if t.zs[k].is_negative() {
+                apply(&mut t, Gate::X(k));
+            }
+        }
+        gates
+    }
+
+    fn append_gate(&mut self, gate: Gate) {
+        for p in self.outputs_mut() {
+            match gate {
+                Gate::H(q) => p.h(q),
+                Gate::S(q) => p.s(q),
+                Gate::SDag(q) => p.s_dag(q),
+                Gate::X(q) => p.x_gate(q),
+                Gate::Z(q) => p.z_gate(q),
+                Gate::Cx(c, t) => p.cx(c, t),
+            }
+        }
+    }
+
+    fn outputs_mut(&mut self) -> impl Iterator<Item = &mut PauliString> {
+        self.xs.iter_mut().chain(self.zs.iter_mut())
+    }
+
+    fn check_targets(&self, gate: &Tableau, targets: &[usize]) {
+        assert_eq!(
+            targets.len(),
+            gate.num_qubits(),
+            "a {}-qubit gate needs that many targets",
+            gate.num_qubits()
+        );
+        for (i, &q) in targets.iter().enumerate() {
+            assert!(q < self.num_qubits(), "target {q} out of range");
+            assert!(!targets[..i].contains(&q), "repeated target {q}");
+        }
+    }
+}
+
+/// Spreads `p` over an `n`-qubit string, qubit `j` landing on `targets[j]`.
+fn place(p: &PauliString, n: usize, targets: &[usize]) -> PauliString {
+    let mut out = PauliString::new(n);
+    for (j, &q) in targets.iter().enumerate() {
+        out.set(q, p.get(j));
+    }
+    out.set_phase(p.phase());
+    out
+}
+
+/// Gates applied when building a tableau or recorded while eliminating one.
+#[derive(Clone, Copy)]
+enum Gate {
+    H(usize),
+    S(usize),
+    SDag(usize),
+    X(usize),
+    Z(usize),
+    Cx(usize, usize),
+}
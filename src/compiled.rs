@@ -0,0 +1,346 @@
+use crate::executor::{Control, controlled_pairs, qubit_pairs, qubits, qubits_with_inversion};
+use crate::ir::{StimInstr, StimTarget};
+use crate::stats::stats;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Basis {
+    X,
+    Y,
+    Z,
+}
+
+/// One instruction of a compiled circuit, with targets already resolved.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Op {
+    H(Vec<usize>),
+    S(Vec<usize>),
+    SDag(Vec<usize>),
+    X(Vec<usize>),
+    Y(Vec<usize>),
+    Z(Vec<usize>),
+    Cx(Vec<(Control, usize)>),
+    Cz(Vec<(Control, usize)>),
+    Measure {
+        basis: Basis,
+        targets: Vec<(usize, bool)>,
+    },
+    Reset {
+        basis: Basis,
+        targets: Vec<usize>,
+    },
+    XError {
+        p: f64,
+        targets: Vec<usize>,
+    },
+    ZError {
+        p: f64,
+        targets: Vec<usize>,
+    },
+    Depolarize1 {
+        p: f64,
+        targets: Vec<usize>,
+    },
+    Depolarize2 {
+        p: f64,
+        pairs: Vec<(usize, usize)>,
+    },
+    /// `recs` are the `rec[-k]` offsets, checked against the record at compile time.
+    Detector {
+        coords: Vec<f64>,
+        recs: Vec<i32>,
+    },
+    ObservableInclude {
+        index: u32,
+        recs: Vec<i32>,
+    },
+    QubitCoords {
+        coords: Vec<f64>,
+        qubits: Vec<u32>,
+    },
+    ShiftCoords(Vec<f64>),
+    Tick,
+    /// Runs the ops up to the matching `EndRepeat` at index `end` `count` times.
+    Repeat {
+        count: u64,
+        end: usize,
+    },
+    /// Closes the loop opened by the `Repeat` at index `start`.
+    EndRepeat {
+        start: usize,
+    },
+}
+
+/// A circuit lowered to flat bytecode that simulators execute without
+/// re-inspecting instruction names or target lists.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompiledCircuit {
+    ops: Vec<Op>,
+    widths: Vec<usize>,
+    max_lookback: usize,
+    num_qubits: usize,
+    num_measurements: u64,
+    num_detectors: u64,
+}
+
+impl CompiledCircuit {
+    pub fn compile(instrs: &[StimInstr]) -> Result<Self, String> {
+        let mut out = Self::empty();
+        for instr in instrs {
+            out.push(instr)?;
+        }
+        Ok(out)
+    }
+
+    /// Compiles instructions as they arrive, lowering each top-level one
+    /// before the next is pulled, so the circuit is never held as a whole.
+    pub fn from_stream(
+        instrs: impl IntoIterator<Item = Result<StimInstr, String>>,
+    ) -> Result<Self, String> {
+        let mut out = Self::empty();
+        for instr in instrs {
+            out.push(&instr?)?;
+        }
+        Ok(out)
+    }
+
+    fn empty() -> Self {
+        Self {
+            ops: Vec::new(),
+            widths: Vec::new(),
+            max_lookback: 0,
+            num_qubits: 0,
+            num_measurements: 0,
+            num_detectors: 0,
+        }
+    }
+
+    /// Lowers one top-level instruction onto the end of the program.
+    fn push(&mut self, instr: &StimInstr) -> Result<(), String> {
+        let start = self.ops.len();
+        let instr = std::slice::from_ref(instr);
+        lower(instr, &mut self.ops, &mut self.num_measurements)?;
+        for op in &self.ops[start..] {
+            self.widths.push(width(op));
+            self.max_lookback = self.max_lookback.max(lookback(op));
+        }
+        let st = stats(instr);
+        self.num_qubits = self.num_qubits.max(st.num_qubits);
+        self.num_detectors = self.num_detectors.saturating_add(st.num_detectors);
+        Ok(())
+    }
+
+    pub fn ops(&self) -> &[Op] {
+        &self.ops
+    }
+
+    pub fn num_qubits(&self) -> usize {
+        self.num_qubits
+    }
+
+    /// The register size op `pc` needs: one past the largest qubit it acts
+    /// on, or 0 for ops that act on none.
+    pub fn width(&self, pc: usize) -> usize {
+        self.widths[pc]
+    }
+
+    /// The largest `k` of any `rec[-k]`, so only that many of the latest
+    /// measurement results ever need to be kept.
+    pub fn max_lookback(&self) -> usize {
+        self.max_lookback
+    }
+
+    pub fn num_measurements(&self) -> u64 {
+        self.num_measurements
+    }
+
+    pub fn num_detectors(&self) -> u64 {
+        self.num_detectors
+    }
+}
+
+/// Loop counters for walking a compiled circuit.
+#[derive(Debug, Default)]
+pub(crate) struct LoopStack {
+    remaining: Vec<u64>,
+}
+
+impl LoopStack {
+    /// Handles `Repeat`/`EndRepeat` at `pc`, returning where to continue;
+    /// returns `None` for any other op.
+    pub fn jump(&mut self, ops: &[Op], pc: usize) -> Option<usize> {
+        match ops[pc] {
+            Op::Repeat { count: 0, end } => Some(end + 1),
+            Op::Repeat { count, .. } => {
+                self.remaining.push(count);
+                Some(pc + 1)
+            }
+            Op::EndRepeat { start } => {
+                let left = self.remaining.last_mut().expect("unbalanced loop");
+                *left -= 1;
+                if *left > 0 {
+                    Some(start + 1)
+                } else {
+                    self.remaining.pop();
+                    Some(pc + 1)
+                }
+            }
+            _ => None,
+        }
+    }
+}
+
+fn lower(instrs: &[StimInstr], ops: &mut Vec<Op>, measured: &mut u64) -> Result<(), String> {
+    for instr in instrs {
+        match instr {
+            StimInstr::Op {
+                name,
+                args,
+                targets,
+                ..
+            } => {
+                let p = args.first().copied().unwrap_or(0.0);
+                if matches!(
+                    name.as_str(),
+                    "X_ERROR" | "Z_ERROR" | "DEPOLARIZE1" | "DEPOLARIZE2"
+                ) && !(0.0..=1.0).contains(&p)
+                {
+                    return Err(format!("{name} probability {p} out of range"));
+                }
+                let op = match name.as_str() {
+                    "H" => Op::H(qubits(targets)?),
+                    "S" => Op::S(qubits(targets)?),
+                    "S_DAG" => Op::SDag(qubits(targets)?),
+                    "X" => Op::X(qubits(targets)?),
+                    "Y" => Op::Y(qubits(targets)?),
+                    "Z" => Op::Z(qubits(targets)?),
+                    "CX" | "CNOT" => Op::Cx(controlled_pairs(targets)?),
+                    "CZ" => Op::Cz(controlled_pairs(targets)?),
+                    "M" | "MX" | "MY" => {
+                        let basis = match name.as_str() {
+                            "MX" => Basis::X,
+                            "MY" => Basis::Y,
+                            _ => Basis::Z,
+                        };
+                        let targets = qubits_with_inversion(targets)?;
+                        *measured += targets.len() as u64;
+                        Op::Measure { basis, targets }
+                    }
+                    "R" | "RZ" | "RX" | "RY" => Op::Reset {
+                        basis: match name.as_str() {
+                            "RX" => Basis::X,
+                            "RY" => Basis::Y,
+                            _ => Basis::Z,
+                        },
+                        targets: qubits(targets)?,
+                    },
+                    "X_ERROR" => Op::XError {
+                        p,
+                        targets: qubits(targets)?,
+                    },
+                    "Z_ERROR" => Op::ZError {
+                        p,
+                        targets: qubits(targets)?,
+                    },
+                    "DEPOLARIZE1" => Op::Depolarize1 {
+                        p,
+                        targets: qubits(targets)?,
+                    },
+                    "DEPOLARIZE2" => Op::Depolarize2 {
+                        p,
+                        pairs: qubit_pairs(targets)?,
+                    },
+                    "DETECTOR" => Op::Detector {
+                        coords: args.clone(),
+                        recs: recs(targets, *measured)?,
+                    },
+                    "OBSERVABLE_INCLUDE" => {
+                        if p.fract() != 0.0 || !(0.0..=u32::MAX as f64).contains(&p) {
+                            return Err(format!("OBSERVABLE_INCLUDE index {p} out of range"));
+                        }
+                        Op::ObservableInclude {
+                            index: p as u32,
+                            recs: recs(targets, *measured)?,
+                        }
+                    }
+                    "QUBIT_COORDS" => Op::QubitCoords {
+                        coords: args.clone(),
+                        qubits: targets
+                            .iter()
+                            .map(|t| match t {
+                                StimTarget::Qubit(q) => Ok(*q),
+                                _ => Err("QUBIT_COORDS expects qubit targets".to_string()),
+                            })
+                            .collect::<Result<_, _>>()?,
+                    },
+                    "SHIFT_COORDS" => Op::ShiftCoords(args.clone()),
+                    "TICK" => Op::Tick,
+                    _ => return Err(format!("unsupported instruction {}", name)),
+                };
+                ops.push(op);
+            }
+            StimInstr::Repeat { count, body } => {
+                let start = ops.len();
+                ops.push(Op::Repeat {
+                    count: *count,
+                    end: 0,
+                });
+                let before = *measured;
+                lower(body, ops, measured)?;
+                let per_iteration = *measured - before;
+                *measured = before.saturating_add(per_iteration.saturating_mul(*count));
+                let end = ops.len();
+                ops.push(Op::EndRepeat { start });
+                ops[start] = Op::Repeat { count: *count, end };
+            }
+        }
+    }
+    Ok(())
+}
+
+fn width(op: &Op) -> usize {
+    let past_max = |qs: &mut dyn Iterator<Item = usize>| qs.map(|q| q + 1).max().unwrap_or(0);
+    match op {
+        Op::H(qs) | Op::S(qs) | Op::SDag(qs) | Op::X(qs) | Op::Y(qs) | Op::Z(qs) => {
+            past_max(&mut qs.iter().copied())
+        }
+        Op::Reset { targets, .. }
+        | Op::XError { targets, .. }
+        | Op::ZError { targets, .. }
+        | Op::Depolarize1 { targets, .. } => past_max(&mut targets.iter().copied()),
+        Op::Cx(pairs) | Op::Cz(pairs) => past_max(&mut pairs.iter().flat_map(|&(c, t)| {
+            let c = match c {
+                Control::Qubit(c) => Some(c),
+                Control::Sweep(_) => None,
+            };
+            c.into_iter().chain([t])
+        })),
+        Op::Measure { targets, .. } => past_max(&mut targets.iter().map(|&(q, _)| q)),
+        Op::Depolarize2 { pairs, .. } => past_max(&mut pairs.iter().flat_map(|&(a, b)| [a, b])),
+        _ => 0,
+    }
+}
+
+fn lookback(op: &Op) -> usize {
+    match op {
+        Op::Detector { recs, .. } | Op::ObservableInclude { recs, .. } => recs
+            .iter()
+            .map(|o| o.unsigned_abs() as usize)
+            .max()
+            .unwrap_or(0),
+        _ => 0,
+    }
+}
+
+/// Collects `rec[-k]` offsets, checking them against the `measured` results
+/// recorded so far (the first loop iteration being the tightest case).
+fn recs(targets: &[StimTarget], measured: u64) -> Result<Vec<i32>, String> {
+    targets
+        .iter()
+        .map(|t| match t {
+            StimTarget::Rec(o) if *o >= 0 => Err("rec must be negative".to_string()),
+            StimTarget::Rec(o) if o.unsigned_abs() as u64 <= measured => Ok(*o),
+            StimTarget::Rec(_) => Err("rec out of range".to_string()),
+            _ => Err("detector target must be rec".to_string()),
+        })
+        .collect()
+}
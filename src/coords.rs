@@ -22,8 +22,8 @@ impl CoordState {
         if out.len() < self.offset.len() {
             out.resize(self.offset.len(), 0.0);
         }
-        for i in 0..self.offset.len() {
-            out[i] += self.offset[i];
+        for (o, d) in out.iter_mut().zip(&self.offset) {
+            *o += d;
         }
         out
     }
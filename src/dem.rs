@@ -0,0 +1,345 @@
+use std::fmt;
+use std::io::BufRead;
+use std::str::FromStr;
+
+use crate::lexer::{Lexer, Token, TokenKind};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum DemTarget {
+    Detector(u64),
+    Observable(u32),
+    /// `^`, separating the graphlike components of a decomposed error.
+    Separator,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DemInstr {
+    Error {
+        probability: f64,
+        targets: Vec<DemTarget>,
+    },
+    Detector {
+        coords: Vec<f64>,
+        index: u64,
+    },
+    LogicalObservable(u32),
+    ShiftDetectors {
+        coords: Vec<f64>,
+        count: u64,
+    },
+    Repeat {
+        count: u64,
+        body: Vec<DemInstr>,
+    },
+}
+
+/// A detector error model: independent error mechanisms and the detectors
+/// and observables each one flips.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DetectorErrorModel {
+    pub instrs: Vec<DemInstr>,
+}
+
+impl DetectorErrorModel {
+    /// Parses Stim's detector error model text format.
+    pub fn parse(input: &str) -> Result<Self, String> {
+        Self::from_reader(input.as_bytes())
+    }
+
+    pub fn from_reader(reader: impl BufRead) -> Result<Self, String> {
+        let mut lexer = Lexer::new(reader);
+        let instrs = parse_block(&mut lexer, false)?;
+        Ok(Self { instrs })
+    }
+
+    pub fn num_detectors(&self) -> u64 {
+        count_detectors(&self.instrs).1
+    }
+
+    pub fn num_observables(&self) -> u32 {
+        count_observables(&self.instrs)
+    }
+}
+
+/// Returns (detector shift after running `instrs`, one past the highest detector mentioned).
+fn count_detectors(instrs: &[DemInstr]) -> (u64, u64) {
+    let mut shift = 0u64;
+    let mut max = 0u64;
+    for instr in instrs {
+        match instr {
+            DemInstr::Error { targets, .. } => {
+                for t in targets {
+                    if let DemTarget::Detector(d) = t {
+                        max = max.max(shift + d + 1);
+                    }
+                }
+            }
+            DemInstr::Detector { index, .. } => max = max.max(shift + index + 1),
+            DemInstr::LogicalObservable(_) => {}
+            DemInstr::ShiftDetectors { count, .. } => shift += count,
+            DemInstr::Repeat { count, body } => {
+                let (inner_shift, inner_max) = count_detectors(body);
+                if *count > 0 && inner_max > 0 {
+                    max = max.max(shift + inner_shift * (count - 1) + inner_max);
+                }
+                shift += inner_shift * count;
+            }
+        }
+    }
+    (shift, max)
+}
+
+fn count_observables(instrs: &[DemInstr]) -> u32 {
+    let mut max = 0u32;
+    for instr in instrs {
+        match instr {
+            DemInstr::Error { targets, .. } => {
+                for t in targets {
+                    if let DemTarget::Observable(o) = t {
+                        max = max.max(o + 1);
+                    }
+                }
+            }
+            DemInstr::LogicalObservable(o) => max = max.max(o + 1),
+            DemInstr::Repeat { body, .. } => max = max.max(count_observables(body)),
+            _ => {}
+        }
+    }
+    max
+}
+
+impl FromStr for DetectorErrorModel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        Self::parse(s)
+    }
+}
+
+fn parse_block<R: BufRead>(lexer: &mut Lexer<R>, nested: bool) -> Result<Vec<DemInstr>, String> {
+    let mut instrs = Vec::new();
+    loop {
+        let Some(token) = lexer.next_token()? else {
+            if nested {
+                return Err("unterminated repeat block".to_string());
+            }
+            return Ok(instrs);
+        };
+        let word = match &token.kind {
+            TokenKind::Newline => continue,
+            TokenKind::RBrace if nested => {
+                expect_end_of_line(lexer)?;
+                return Ok(instrs);
+            }
+            TokenKind::Word(w) => w.to_ascii_lowercase(),
+            _ => return Err(format!("{}: expected instruction name", token.describe())),
+        };
+        let args = parse_args(lexer)?;
+        let words = read_words(lexer)?;
+        let at = |msg: &str| format!("{}: {msg}", token.describe());
+        let instr = match word.as_str() {
+            "error" => {
+                let [probability] = args[..] else {
+                    return Err(at("error takes one probability"));
+                };
+                if !(0.0..=1.0).contains(&probability) {
+                    return Err(at("probability must be in [0, 1]"));
+                }
+                let targets = words
+                    .iter()
+                    .map(|w| parse_dem_target(w).map_err(|e| at(&e)))
+                    .collect::<Result<_, _>>()?;
+                DemInstr::Error {
+                    probability,
+                    targets,
+                }
+            }
+            "detector" => match words.as_slice() {
+                [w] => match parse_dem_target(w).map_err(|e| at(&e))? {
+                    DemTarget::Detector(index) => DemInstr::Detector {
+                        coords: args,
+                        index,
+                    },
+                    _ => return Err(at("detector expects a D target")),
+                },
+                _ => return Err(at("detector expects one target")),
+            },
+            "logical_observable" => match words.as_slice() {
+                [w] if args.is_empty() => match parse_dem_target(w).map_err(|e| at(&e))? {
+                    DemTarget::Observable(o) => DemInstr::LogicalObservable(o),
+                    _ => return Err(at("logical_observable expects an L target")),
+                },
+                _ => return Err(at("logical_observable expects one target")),
+            },
+            "shift_detectors" => match words.as_slice() {
+                [w] => DemInstr::ShiftDetectors {
+                    coords: args,
+                    count: w.parse().map_err(|_| at("bad shift count"))?,
+                },
+                _ => return Err(at("shift_detectors expects one count")),
+            },
+            "repeat" => {
+                let count = match words.as_slice() {
+                    [w] if args.is_empty() => {
+                        w.parse::<u64>().map_err(|_| at("bad repeat count"))?
+                    }
+                    _ => return Err(at("repeat expects one count")),
+                };
+                skip_newlines(lexer)?;
+                match lexer.next_token()? {
+                    Some(Token {
+                        kind: TokenKind::LBrace,
+                        ..
+                    }) => {}
+                    Some(t) => return Err(format!("{}: expected {{", t.describe())),
+                    None => return Err("unterminated repeat block".to_string()),
+                }
+                let body = parse_block(lexer, true)?;
+                DemInstr::Repeat { count, body }
+            }
+            _ => return Err(at("unknown instruction")),
+        };
+        instrs.push(instr);
+    }
+}
+
+fn parse_args<R: BufRead>(lexer: &mut Lexer<R>) -> Result<Vec<f64>, String> {
+    if !matches!(lexer.peek_kind()?, Some(TokenKind::LParen)) {
+        return Ok(vec![]);
+    }
+    lexer.next_token()?;
+    let mut args = Vec::new();
+    loop {
+        let token = lexer
+            .next_token()?
+            .ok_or_else(|| format!("line {}: unterminated argument list", lexer.line_no()))?;
+        match &token.kind {
+            TokenKind::RParen if args.is_empty() => return Ok(args),
+            TokenKind::Word(w) => match w.parse::<f64>() {
+                Ok(v) if v.is_finite() => args.push(v),
+                _ => return Err(format!("{}: bad argument", token.describe())),
+            },
+            _ => return Err(format!("{}: expected argument", token.describe())),
+        }
+        let token = lexer
+            .next_token()?
+            .ok_or_else(|| format!("line {}: unterminated argument list", lexer.line_no()))?;
+        match &token.kind {
+            TokenKind::Comma => {}
+            TokenKind::RParen => return Ok(args),
+            _ => return Err(format!("{}: expected , or )", token.describe())),
+        }
+    }
+}
+
+/// Reads the remaining words on the line, stopping before a `{`.
+fn read_words<R: BufRead>(lexer: &mut Lexer<R>) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    loop {
+        match lexer.peek_kind()? {
+            None | Some(TokenKind::LBrace) => return Ok(words),
+            Some(TokenKind::Newline) => {
+                lexer.next_token()?;
+                return Ok(words);
+            }
+            Some(TokenKind::Word(_)) => {
+                if let Some(Token {
+                    kind: TokenKind::Word(w),
+                    ..
+                }) = lexer.next_token()?
+                {
+                    words.push(w);
+                }
+            }
+            Some(_) => {
+                let t = lexer.next_token()?.expect("peeked token");
+                return Err(format!("{}: unexpected token", t.describe()));
+            }
+        }
+    }
+}
+
+fn skip_newlines<R: BufRead>(lexer: &mut Lexer<R>) -> Result<(), String> {
+    while matches!(lexer.peek_kind()?, Some(TokenKind::Newline)) {
+        lexer.next_token()?;
+    }
+    Ok(())
+}
+
+fn expect_end_of_line<R: BufRead>(lexer: &mut Lexer<R>) -> Result<(), String> {
+    match lexer.next_token()? {
+        None
+        | Some(Token {
+            kind: TokenKind::Newline,
+            ..
+        }) => Ok(()),
+        Some(t) => Err(format!("{}: expected end of line", t.describe())),
+    }
+}
+
+fn parse_dem_target(word: &str) -> Result<DemTarget, String> {
+    if word == "^" {
+        return Ok(DemTarget::Separator);
+    }
+    let parsed = match word.split_at_checked(1) {
+        Some(("D", n)) => n.parse().ok().map(DemTarget::Detector),
+        Some(("L", n)) => n.parse().ok().map(DemTarget::Observable),
+        _ => None,
+    };
+    parsed.ok_or_else(|| format!("bad target {word}"))
+}
+
+impl fmt::Display for DemTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DemTarget::Detector(d) => write!(f, "D{d}"),
+            DemTarget::Observable(o) => write!(f, "L{o}"),
+            DemTarget::Separator => write!(f, "^"),
+        }
+    }
+}
+
+impl fmt::Display for DetectorErrorModel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_instrs(f, &self.instrs, 0)
+    }
+}
+
+fn write_instrs(f: &mut fmt::Formatter<'_>, instrs: &[DemInstr], indent: usize) -> fmt::Result {
+    let pad = "    ".repeat(indent);
+    for instr in instrs {
+        match instr {
+            DemInstr::Error {
+                probability,
+                targets,
+            } => {
+                write!(f, "{pad}error({probability})")?;
+                for t in targets {
+                    write!(f, " {t}")?;
+                }
+                writeln!(f)?;
+            }
+            DemInstr::Detector { coords, index } => {
+                writeln!(f, "{pad}detector{} D{index}", format_coords(coords))?;
+            }
+            DemInstr::LogicalObservable(o) => writeln!(f, "{pad}logical_observable L{o}")?,
+            DemInstr::ShiftDetectors { coords, count } => {
+                writeln!(f, "{pad}shift_detectors{} {count}", format_coords(coords))?;
+            }
+            DemInstr::Repeat { count, body } => {
+                writeln!(f, "{pad}repeat {count} {{")?;
+                write_instrs(f, body, indent + 1)?;
+                writeln!(f, "{pad}}}")?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn format_coords(coords: &[f64]) -> String {
+    if coords.is_empty() {
+        return String::new();
+    }
+    let parts: Vec<String> = coords.iter().map(|c| c.to_string()).collect();
+    format!("({})", parts.join(", "))
+}
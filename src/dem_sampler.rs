@@ -0,0 +1,139 @@
+use rand::RngCore;
+
+use crate::dem::{DemInstr, DemTarget, DetectorErrorModel};
+use crate::sampler::CHUNK_SHOTS;
+use crate::seed::{ShotRng, hits};
+
+/// One error mechanism with its detector shift already applied.
+#[derive(Debug, Clone)]
+struct Mechanism {
+    probability: f64,
+    detectors: Vec<usize>,
+    observables: Vec<usize>,
+}
+
+/// Detector and observable flips of a single shot.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DemShot {
+    pub detectors: Vec<bool>,
+    pub observables: Vec<bool>,
+}
+
+/// Samples shots from a detector error model by firing each error
+/// mechanism independently, without simulating the circuit. Each mechanism
+/// skips geometrically across the batch, so rare errors cost almost nothing.
+#[derive(Debug, Clone)]
+pub struct DemSampler {
+    mechanisms: Vec<Mechanism>,
+    num_detectors: usize,
+    num_observables: usize,
+}
+
+impl DemSampler {
+    pub fn new(dem: &DetectorErrorModel) -> Self {
+        let mut mechanisms = Vec::new();
+        let mut shift = 0u64;
+        flatten(&dem.instrs, &mut shift, &mut mechanisms);
+        Self {
+            mechanisms,
+            num_detectors: dem.num_detectors() as usize,
+            num_observables: dem.num_observables() as usize,
+        }
+    }
+
+    pub fn num_detectors(&self) -> usize {
+        self.num_detectors
+    }
+
+    pub fn num_observables(&self) -> usize {
+        self.num_observables
+    }
+
+    pub fn sample(&self, rng: &mut impl RngCore) -> DemShot {
+        self.sample_shots(1, rng).pop().unwrap_or_default()
+    }
+
+    pub fn sample_shots(&self, shots: usize, rng: &mut impl RngCore) -> Vec<DemShot> {
+        let mut out = vec![
+            DemShot {
+                detectors: vec![false; self.num_detectors],
+                observables: vec![false; self.num_observables],
+            };
+            shots
+        ];
+        for m in &self.mechanisms {
+            for shot in hits(rng, m.probability, shots) {
+                apply(m, &mut out[shot]);
+            }
+        }
+        out
+    }
+
+    /// Samples shots `first_shot..first_shot + shots` under `seed`. As in
+    /// [`Sampler`](crate::sampler::Sampler), block `b` of [`CHUNK_SHOTS`]
+    /// shots is drawn whole from `ShotRng::new(seed, b)`, so a shot depends
+    /// only on the seed and its index.
+    pub fn sample_range(&self, seed: u64, first_shot: u64, shots: usize) -> Vec<DemShot> {
+        let block = CHUNK_SHOTS as u64;
+        let end = first_shot + shots as u64;
+        let mut out = Vec::with_capacity(shots);
+        for b in first_shot / block..end.div_ceil(block) {
+            let drawn = self.sample_shots(CHUNK_SHOTS, &mut ShotRng::new(seed, b));
+            let skip = first_shot.saturating_sub(b * block) as usize;
+            let take = (end - b * block).min(block) as usize;
+            out.extend(drawn.into_iter().take(take).skip(skip));
+        }
+        out
+    }
+}
+
+fn apply(m: &Mechanism, shot: &mut DemShot) {
+    for &d in &m.detectors {
+        shot.detectors[d] ^= true;
+    }
+    for &o in &m.observables {
+        shot.observables[o] ^= true;
+    }
+}
+
+fn flatten(instrs: &[DemInstr], shift: &mut u64, out: &mut Vec<Mechanism>) {
+    for instr in instrs {
+        match instr {
+            DemInstr::Error {
+                probability,
+                targets,
+            } => {
+                let mut m = Mechanism {
+                    probability: *probability,
+                    detectors: Vec::new(),
+                    observables: Vec::new(),
+                };
+                // Repeated targets cancel; separators only mark decomposition.
+                for t in targets {
+                    match t {
+                        DemTarget::Detector(d) => toggle(&mut m.detectors, (d + *shift) as usize),
+                        DemTarget::Observable(o) => toggle(&mut m.observables, *o as usize),
+                        DemTarget::Separator => {}
+                    }
+                }
+                out.push(m);
+            }
+            DemInstr::ShiftDetectors { count, .. } => *shift += count,
+            DemInstr::Repeat { count, body } => {
+                for _ in 0..*count {
+                    flatten(body, shift, out);
+                }
+            }
+            DemInstr::Detector { .. } | DemInstr::LogicalObservable(_) => {}
+        }
+    }
+}
+
+fn toggle(list: &mut Vec<usize>, x: usize) {
+    match list.iter().position(|&y| y == x) {
+        Some(i) => {
+            list.swap_remove(i);
+        }
+        None => list.push(x),
+    }
+}
@@ -0,0 +1,821 @@
+use std::collections::{BTreeMap, HashMap};
+
+use crate::dem::{DemInstr, DemTarget, DetectorErrorModel};
+use crate::executor::{Control, controlled_pairs, qubit_pairs, qubits, qubits_with_inversion};
+use crate::ir::{StimInstr, StimTarget};
+use crate::pauli::{Pauli, two_qubit_pauli};
+use crate::stats::stats;
+
+#[derive(Debug, Clone, Default)]
+pub struct ErrorAnalyzerOptions {
+    /// Split errors flipping more than two detectors into `^`-separated
+    /// components that each flip at most two.
+    pub decompose_errors: bool,
+    /// Emit `REPEAT` blocks, nested ones included, as `repeat` blocks of the
+    /// model. A loop is analysed from its last iteration backwards until an
+    /// iteration turns out to repeat the one after it; the iterations before
+    /// are then folded into its block without being analysed.
+    pub fold_loops: bool,
+    /// Allow channels whose Pauli cases are disjoint to be approximated by
+    /// independent errors. Every channel supported today (`X_ERROR`,
+    /// `Z_ERROR`, `DEPOLARIZE1`, `DEPOLARIZE2`) converts exactly, so this
+    /// has no effect yet.
+    pub approximate_disjoint_errors: bool,
+    /// Keep errors that cannot be decomposed undecomposed instead of failing.
+    pub ignore_decomposition_failures: bool,
+}
+
+/// Builds the detector error model of a circuit by walking it backwards,
+/// tracking which detectors and observables an X or Z flip of each qubit
+/// would toggle, and reading the Pauli cases of each noise channel off the
+/// qubits it acts on.
+pub fn analyze_errors(
+    instrs: &[StimInstr],
+    opts: &ErrorAnalyzerOptions,
+) -> Result<DetectorErrorModel, String> {
+    let st = stats(instrs);
+    let mut a = Analyzer {
+        fold_loops: opts.fold_loops,
+        xs: vec![Symptoms::default(); st.num_qubits],
+        zs: vec![Symptoms::default(); st.num_qubits],
+        recs: BTreeMap::new(),
+        num_measurements: st.num_measurements,
+        num_detectors: st.num_detectors,
+        sites: Vec::new(),
+        detectors: Vec::new(),
+        segments: Vec::new(),
+        parent: None,
+        num_loops: 0,
+    };
+    a.run(instrs, &[])?;
+    a.build_model(st.num_detectors, st.num_observables, opts)
+}
+
+/// The detectors and observables a flip toggles, each sorted.
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Symptoms {
+    dets: Vec<u64>,
+    obs: Vec<u32>,
+}
+
+impl Symptoms {
+    fn xor(&mut self, other: &Symptoms) {
+        self.dets = xor_sorted(&self.dets, &other.dets);
+        self.obs = xor_sorted(&self.obs, &other.obs);
+    }
+
+    fn is_empty(&self) -> bool {
+        self.dets.is_empty() && self.obs.is_empty()
+    }
+
+    /// Renumbers detectors, failing if any falls out of range.
+    fn map_dets(&self, f: impl Fn(u64) -> Option<u64>) -> Option<Self> {
+        Some(Self {
+            dets: self.dets.iter().map(|&d| f(d)).collect::<Option<_>>()?,
+            obs: self.obs.clone(),
+        })
+    }
+}
+
+/// One noise channel applied to one target (or target pair), with the
+/// symptoms of the X then Z component of each qubit involved.
+struct Site {
+    kind: SiteKind,
+    probability: f64,
+    components: Vec<Symptoms>,
+    segment: usize,
+}
+
+impl Site {
+    fn symptoms(&self, parts: &[usize]) -> Symptoms {
+        let mut out = Symptoms::default();
+        for &p in parts {
+            out.xor(&self.components[p]);
+        }
+        out
+    }
+}
+
+#[derive(Clone, Copy)]
+enum SiteKind {
+    XError,
+    ZError,
+    Depolarize1,
+    Depolarize2,
+}
+
+impl SiteKind {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "X_ERROR" => Some(SiteKind::XError),
+            "Z_ERROR" => Some(SiteKind::ZError),
+            "DEPOLARIZE1" => Some(SiteKind::Depolarize1),
+            "DEPOLARIZE2" => Some(SiteKind::Depolarize2),
+            _ => None,
+        }
+    }
+}
+
+struct DetectorInfo {
+    coords: Vec<f64>,
+    index: u64,
+    segment: usize,
+}
+
+/// A run of instructions, or `repeat` consecutive iterations of a loop body
+/// that all produce the same errors. An iteration holds the segments of its
+/// body as children.
+struct Segment {
+    loop_id: Option<usize>,
+    parent: Option<usize>,
+    repeat: u64,
+    /// Detectors declared by one iteration.
+    num_detectors: u64,
+    /// Coordinate shift of one iteration.
+    delta: Vec<f64>,
+}
+
+/// What the analysis carries from one point of the circuit to the point
+/// before it.
+#[derive(PartialEq)]
+struct Tracked {
+    xs: Vec<Symptoms>,
+    zs: Vec<Symptoms>,
+    recs: BTreeMap<u64, Symptoms>,
+}
+
+impl Tracked {
+    fn map(
+        &self,
+        det: impl Fn(u64) -> Option<u64> + Copy,
+        meas: impl Fn(u64) -> Option<u64>,
+    ) -> Option<Self> {
+        let map_all =
+            |s: &[Symptoms]| -> Option<Vec<_>> { s.iter().map(|s| s.map_dets(det)).collect() };
+        Some(Self {
+            xs: map_all(&self.xs)?,
+            zs: map_all(&self.zs)?,
+            recs: self
+                .recs
+                .iter()
+                .map(|(&m, s)| Some((meas(m)?, s.map_dets(det)?)))
+                .collect::<Option<_>>()?,
+        })
+    }
+}
+
+/// Walks a circuit backwards. Sites, detectors and segments are recorded in
+/// the order they are met, last first.
+struct Analyzer {
+    fold_loops: bool,
+    /// Symptoms of an X (resp. Z) flip of each qubit at the current point.
+    xs: Vec<Symptoms>,
+    zs: Vec<Symptoms>,
+    /// Symptoms of flipping each measurement still ahead in the walk.
+    recs: BTreeMap<u64, Symptoms>,
+    /// Measurements and detectors before the current point.
+    num_measurements: u64,
+    num_detectors: u64,
+    sites: Vec<Site>,
+    detectors: Vec<DetectorInfo>,
+    segments: Vec<Segment>,
+    /// The loop iteration being walked.
+    parent: Option<usize>,
+    num_loops: usize,
+}
+
+impl Analyzer {
+    /// Walks a loop backwards an iteration at a time. Once an iteration
+    /// leaves the analysis as the one after it did, shifted back by one
+    /// iteration's measurements and detectors, every earlier iteration would
+    /// produce the same errors again, so they all join one segment.
+    fn fold_loop(&mut self, count: u64, body: &[StimInstr], offset: &[f64]) -> Result<(), String> {
+        let loop_id = self.num_loops;
+        self.num_loops += 1;
+        let inner = stats(body);
+        let (dets, meas) = (inner.num_detectors, inner.num_measurements);
+        let delta = coord_shift(body);
+        for iteration in (0..count).rev() {
+            let after = self.tracked();
+            let marks = (self.sites.len(), self.detectors.len(), self.segments.len());
+            let start = add_coords(offset, &scale_coords(&delta, iteration));
+            self.iteration(loop_id, 1, body, &start)?;
+            if iteration == 0
+                || self
+                    .tracked()
+                    .map(|d| Some(d + dets), |m| Some(m + meas))
+                    .as_ref()
+                    != Some(&after)
+            {
+                continue;
+            }
+            // Redo the analysis as the loop's first iteration, standing for
+            // all of them up to this one.
+            let Some(first) = after.map(
+                |d| d.checked_sub(iteration * dets),
+                |m| m.checked_sub(iteration * meas),
+            ) else {
+                continue;
+            };
+            self.sites.truncate(marks.0);
+            self.detectors.truncate(marks.1);
+            self.segments.truncate(marks.2);
+            self.xs = first.xs;
+            self.zs = first.zs;
+            self.recs = first.recs;
+            self.num_measurements -= (iteration - 1) * meas;
+            self.num_detectors -= (iteration - 1) * dets;
+            self.iteration(loop_id, iteration + 1, body, offset)?;
+            break;
+        }
+        Ok(())
+    }
+
+    /// Walks one iteration of a loop as a segment standing for `repeat`.
+    fn iteration(
+        &mut self,
+        loop_id: usize,
+        repeat: u64,
+        body: &[StimInstr],
+        offset: &[f64],
+    ) -> Result<(), String> {
+        let inner = stats(body);
+        self.segments.push(Segment {
+            loop_id: Some(loop_id),
+            parent: self.parent,
+            repeat,
+            num_detectors: inner.num_detectors,
+            delta: coord_shift(body),
+        });
+        let parent = self.parent.replace(self.segments.len() - 1);
+        self.run(body, offset)?;
+        self.parent = parent;
+        Ok(())
+    }
+
+    /// Starts a segment for plain instructions unless the last one is such
+    /// a segment of the same iteration.
+    fn plain_segment(&mut self) {
+        if let Some(last) = self.segments.last()
+            && last.loop_id.is_none()
+            && last.parent == self.parent
+        {
+            return;
+        }
+        self.segments.push(Segment {
+            loop_id: None,
+            parent: self.parent,
+            repeat: 1,
+            num_detectors: 0,
+            delta: Vec::new(),
+        });
+    }
+
+    fn tracked(&self) -> Tracked {
+        Tracked {
+            xs: self.xs.clone(),
+            zs: self.zs.clone(),
+            recs: self.recs.clone(),
+        }
+    }
+
+    /// Walks `instrs` backwards, given the coordinate offset they start at.
+    fn run(&mut self, instrs: &[StimInstr], offset: &[f64]) -> Result<(), String> {
+        // Coordinate offsets accumulate forwards, so they are found up front.
+        let mut offsets = vec![offset.to_vec()];
+        for instr in instrs {
+            let shift = coord_shift(std::slice::from_ref(instr));
+            offsets.push(add_coords(offsets.last().unwrap(), &shift));
+        }
+        for (instr, offset) in instrs.iter().zip(&offsets).rev() {
+            match instr {
+                StimInstr::Op {
+                    name,
+                    args,
+                    targets,
+                    ..
+                } => {
+                    self.plain_segment();
+                    self.op(name, args, targets, offset)?;
+                }
+                StimInstr::Repeat { count, body } if self.fold_loops => {
+                    self.fold_loop(*count, body, offset)?;
+                }
+                StimInstr::Repeat { count, body } => {
+                    let delta = coord_shift(body);
+                    for k in (0..*count).rev() {
+                        self.run(body, &add_coords(offset, &scale_coords(&delta, k)))?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Moves the tracked symptoms from just after an instruction to just
+    /// before it. `offset` is the coordinate offset in force there.
+    fn op(
+        &mut self,
+        name: &str,
+        args: &[f64],
+        targets: &[StimTarget],
+        offset: &[f64],
+    ) -> Result<(), String> {
+        match name {
+            "H" => {
+                for q in qubits(targets)? {
+                    std::mem::swap(&mut self.xs[q], &mut self.zs[q]);
+                }
+            }
+            "S" | "S_DAG" => {
+                for q in qubits(targets)? {
+                    self.xs[q].xor(&self.zs[q]);
+                }
+            }
+            "X" | "Y" | "Z" => {
+                qubits(targets)?;
+            }
+            "CX" | "CNOT" => {
+                for (c, t) in controlled_pairs(targets)?.into_iter().rev() {
+                    if let Control::Qubit(c) = c {
+                        let x = self.xs[t].clone();
+                        self.xs[c].xor(&x);
+                        let z = self.zs[c].clone();
+                        self.zs[t].xor(&z);
+                    }
+                }
+            }
+            "CZ" => {
+                for (c, t) in controlled_pairs(targets)?.into_iter().rev() {
+                    if let Control::Qubit(c) = c {
+                        self.xs[c].xor(&self.zs[t]);
+                        self.xs[t].xor(&self.zs[c]);
+                    }
+                }
+            }
+            "M" | "MX" | "MY" => {
+                for (q, _) in qubits_with_inversion(targets)?.into_iter().rev() {
+                    self.num_measurements -= 1;
+                    let flips = self.recs.remove(&self.num_measurements).unwrap_or_default();
+                    if name != "MX" {
+                        self.xs[q].xor(&flips);
+                    }
+                    if name != "M" {
+                        self.zs[q].xor(&flips);
+                    }
+                }
+            }
+            "R" | "RZ" | "RX" | "RY" => {
+                for q in qubits(targets)? {
+                    self.xs[q] = Symptoms::default();
+                    self.zs[q] = Symptoms::default();
+                }
+            }
+            "X_ERROR" | "Z_ERROR" | "DEPOLARIZE1" | "DEPOLARIZE2" => {
+                let kind = SiteKind::from_name(name).unwrap();
+                let p = args.first().copied().unwrap_or(0.0);
+                let groups: Vec<Vec<usize>> = match kind {
+                    SiteKind::Depolarize2 => qubit_pairs(targets)?
+                        .into_iter()
+                        .map(|(a, b)| vec![a, b])
+                        .collect(),
+                    _ => qubits(targets)?.into_iter().map(|q| vec![q]).collect(),
+                };
+                for group in groups.into_iter().rev() {
+                    let mut components = Vec::new();
+                    for q in group {
+                        match kind {
+                            SiteKind::XError => components.push(self.xs[q].clone()),
+                            SiteKind::ZError => components.push(self.zs[q].clone()),
+                            _ => {
+                                components.push(self.xs[q].clone());
+                                components.push(self.zs[q].clone());
+                            }
+                        }
+                    }
+                    self.sites.push(Site {
+                        kind,
+                        probability: p,
+                        components,
+                        segment: self.segments.len() - 1,
+                    });
+                }
+            }
+            "DETECTOR" => {
+                self.num_detectors -= 1;
+                let index = self.num_detectors;
+                self.toggle_recs(
+                    targets,
+                    &Symptoms {
+                        dets: vec![index],
+                        obs: vec![],
+                    },
+                )?;
+                self.detectors.push(DetectorInfo {
+                    coords: if args.is_empty() {
+                        vec![]
+                    } else {
+                        add_coords(args, offset)
+                    },
+                    index,
+                    segment: self.segments.len() - 1,
+                });
+            }
+            "OBSERVABLE_INCLUDE" => {
+                let index = args.first().copied().unwrap_or(0.0) as u32;
+                self.toggle_recs(
+                    targets,
+                    &Symptoms {
+                        dets: vec![],
+                        obs: vec![index],
+                    },
+                )?;
+            }
+            "SHIFT_COORDS" | "QUBIT_COORDS" | "TICK" => {}
+            _ => return Err(format!("unsupported instruction {name}")),
+        }
+        Ok(())
+    }
+
+    /// Adds `symptoms` to every measurement `targets` refer back to.
+    fn toggle_recs(&mut self, targets: &[StimTarget], symptoms: &Symptoms) -> Result<(), String> {
+        for t in targets {
+            match t {
+                StimTarget::Rec(o) => {
+                    let back = o.unsigned_abs() as u64;
+                    if back > self.num_measurements {
+                        return Err("rec out of range".to_string());
+                    }
+                    self.recs
+                        .entry(self.num_measurements - back)
+                        .or_default()
+                        .xor(symptoms);
+                }
+                _ => return Err("detector target must be rec".to_string()),
+            }
+        }
+        Ok(())
+    }
+
+    fn build_model(
+        self,
+        num_detectors: u64,
+        num_observables: u64,
+        opts: &ErrorAnalyzerOptions,
+    ) -> Result<DetectorErrorModel, String> {
+        // Everything was recorded last first; put it back in circuit order.
+        let n = self.segments.len();
+        let mut children = vec![Vec::new(); n];
+        let mut roots = Vec::new();
+        for (id, seg) in self.segments.iter().enumerate().rev() {
+            match seg.parent {
+                Some(parent) => children[parent].push(id),
+                None => roots.push(id),
+            }
+        }
+        let mut sites = vec![Vec::new(); n];
+        for site in self.sites.iter().rev() {
+            sites[site.segment].push(site);
+        }
+        let mut detectors = vec![Vec::new(); n];
+        for det in self.detectors.iter().rev() {
+            if !det.coords.is_empty() {
+                detectors[det.segment].push(det);
+            }
+        }
+        let mut out = Emitter {
+            segments: &self.segments,
+            opts,
+            children,
+            sites,
+            detectors,
+            shift: 0,
+            coords: Vec::new(),
+            mentioned_obs: vec![false; num_observables as usize],
+        };
+        let instrs = out.emit(&roots)?;
+
+        let mut model = DetectorErrorModel { instrs };
+        if model.num_detectors() < num_detectors {
+            // A last detector inside a loop is only reachable before the
+            // loop's shifts.
+            let last = num_detectors - 1;
+            match last.checked_sub(out.shift) {
+                Some(index) => model.instrs.push(DemInstr::Detector {
+                    coords: vec![],
+                    index,
+                }),
+                None => model.instrs.insert(
+                    0,
+                    DemInstr::Detector {
+                        coords: vec![],
+                        index: last,
+                    },
+                ),
+            }
+        }
+        for (index, mentioned) in out.mentioned_obs.iter().enumerate() {
+            if !mentioned {
+                model.instrs.push(DemInstr::LogicalObservable(index as u32));
+            }
+        }
+        Ok(model)
+    }
+}
+
+/// Writes segments out as model instructions in circuit order, following
+/// the detector and coordinate shift of the model written so far.
+struct Emitter<'a> {
+    segments: &'a [Segment],
+    opts: &'a ErrorAnalyzerOptions,
+    children: Vec<Vec<usize>>,
+    sites: Vec<Vec<&'a Site>>,
+    detectors: Vec<Vec<&'a DetectorInfo>>,
+    shift: u64,
+    coords: Vec<f64>,
+    mentioned_obs: Vec<bool>,
+}
+
+impl Emitter<'_> {
+    fn emit(&mut self, ids: &[usize]) -> Result<Vec<DemInstr>, String> {
+        let mut blocks: Vec<(Option<usize>, u64, Vec<DemInstr>)> = Vec::new();
+        for &id in ids {
+            let seg = &self.segments[id];
+            let block = if seg.loop_id.is_some() {
+                self.iteration(id)?
+            } else {
+                self.plain(id)?
+            };
+            blocks.push((seg.loop_id, seg.repeat, block));
+        }
+
+        let mut instrs: Vec<DemInstr> = Vec::new();
+        let mut i = 0;
+        while i < blocks.len() {
+            let mut j = i + 1;
+            let mut count = blocks[i].1;
+            if blocks[i].0.is_some() {
+                while j < blocks.len()
+                    && blocks[j].0 == blocks[i].0
+                    && blocks_match(&blocks[j].2, &blocks[i].2)
+                {
+                    count += blocks[j].1;
+                    j += 1;
+                }
+            }
+            let body = std::mem::take(&mut blocks[i].2);
+            if count > 1 {
+                instrs.push(DemInstr::Repeat { count, body });
+            } else {
+                instrs.extend(body);
+            }
+            i = j;
+        }
+        Ok(instrs)
+    }
+
+    /// The body of a loop segment, ending with whatever shift its nested
+    /// loops have not already applied.
+    fn iteration(&mut self, id: usize) -> Result<Vec<DemInstr>, String> {
+        let seg = &self.segments[id];
+        let (shift, coords) = (self.shift, self.coords.clone());
+        let children = std::mem::take(&mut self.children[id]);
+        let mut block = self.emit(&children)?;
+        let mut count = seg.num_detectors;
+        let mut delta = seg.delta.clone();
+        for &c in &children {
+            let child = &self.segments[c];
+            if child.loop_id.is_some() {
+                count -= child.repeat * child.num_detectors;
+                delta = sub_coords(&delta, &scale_coords(&child.delta, child.repeat));
+            }
+        }
+        let moved = delta.iter().any(|&c| c != 0.0);
+        if count > 0 || moved {
+            block.push(DemInstr::ShiftDetectors {
+                coords: if moved { delta } else { vec![] },
+                count,
+            });
+        }
+        self.shift = shift + seg.repeat * seg.num_detectors;
+        self.coords = add_coords(&coords, &scale_coords(&seg.delta, seg.repeat));
+        Ok(block)
+    }
+
+    /// The merged errors and coordinate detectors of a plain segment.
+    fn plain(&mut self, id: usize) -> Result<Vec<DemInstr>, String> {
+        let base = self.shift;
+        let mut errors: Vec<(Vec<DemTarget>, f64)> = Vec::new();
+        let mut seen: HashMap<Vec<DemTarget>, usize> = HashMap::new();
+        for site in std::mem::take(&mut self.sites[id]) {
+            for (probability, parts) in site_mechanisms(site)? {
+                let symptoms = site.symptoms(&parts);
+                if probability == 0.0 || symptoms.is_empty() {
+                    continue;
+                }
+                let components = if self.opts.decompose_errors && symptoms.dets.len() > 2 {
+                    let comps: Vec<_> = parts
+                        .iter()
+                        .map(|&p| site.symptoms(&[p]))
+                        .filter(|s| !s.is_empty())
+                        .collect();
+                    if comps.iter().all(|s| s.dets.len() <= 2) {
+                        comps
+                    } else if self.opts.ignore_decomposition_failures {
+                        vec![symptoms]
+                    } else {
+                        return Err(format!(
+                            "failed to decompose error {} into graphlike components",
+                            format_symptoms(&symptoms.dets, &symptoms.obs)
+                        ));
+                    }
+                } else {
+                    vec![symptoms]
+                };
+                let mut targets = Vec::new();
+                for (i, s) in components.iter().enumerate() {
+                    if i > 0 {
+                        targets.push(DemTarget::Separator);
+                    }
+                    targets.extend(s.dets.iter().map(|&d| DemTarget::Detector(d - base)));
+                    targets.extend(s.obs.iter().map(|&o| DemTarget::Observable(o)));
+                    for &o in &s.obs {
+                        self.mentioned_obs[o as usize] = true;
+                    }
+                }
+                match seen.get(&targets) {
+                    Some(&i) => {
+                        let p = errors[i].1;
+                        errors[i].1 = p + probability - 2.0 * p * probability;
+                    }
+                    None => {
+                        seen.insert(targets.clone(), errors.len());
+                        errors.push((targets, probability));
+                    }
+                }
+            }
+        }
+        let mut block: Vec<DemInstr> = errors
+            .into_iter()
+            .map(|(targets, probability)| DemInstr::Error {
+                probability,
+                targets,
+            })
+            .collect();
+        for det in &self.detectors[id] {
+            block.push(DemInstr::Detector {
+                coords: sub_coords(&det.coords, &self.coords),
+                index: det.index - base,
+            });
+        }
+        Ok(block)
+    }
+}
+
+/// The independent Pauli cases of a site as (probability, components).
+fn site_mechanisms(site: &Site) -> Result<Vec<(f64, Vec<usize>)>, String> {
+    let p = site.probability;
+    if !(0.0..=1.0).contains(&p) {
+        return Err(format!("error probability {p} out of range"));
+    }
+    // Components of a Pauli on the qubit whose X component is `base`.
+    let pauli_parts = |pauli: Pauli, base: usize| {
+        let (x, z) = pauli.xz();
+        let mut parts = Vec::new();
+        if x {
+            parts.push(base);
+        }
+        if z {
+            parts.push(base + 1);
+        }
+        parts
+    };
+    Ok(match site.kind {
+        SiteKind::XError | SiteKind::ZError => vec![(p, vec![0])],
+        SiteKind::Depolarize1 => {
+            if p > 0.75 {
+                return Err(format!("DEPOLARIZE1 probability {p} exceeds 3/4"));
+            }
+            let q = 0.5 - 0.5 * (1.0 - 4.0 * p / 3.0).sqrt();
+            Pauli::NON_IDENTITY
+                .iter()
+                .map(|&pauli| (q, pauli_parts(pauli, 0)))
+                .collect()
+        }
+        SiteKind::Depolarize2 => {
+            if p > 15.0 / 16.0 {
+                return Err(format!("DEPOLARIZE2 probability {p} exceeds 15/16"));
+            }
+            let q = 0.5 - 0.5 * (1.0 - 16.0 * p / 15.0).powf(0.125);
+            (0..15)
+                .map(|r| {
+                    let (pa, pb) = two_qubit_pauli(r);
+                    let mut parts = pauli_parts(pa, 0);
+                    parts.extend(pauli_parts(pb, 2));
+                    (q, parts)
+                })
+                .collect()
+        }
+    })
+}
+
+fn xor_sorted<T: Ord + Copy>(a: &[T], b: &[T]) -> Vec<T> {
+    let mut out = Vec::with_capacity(a.len() + b.len());
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        match a[i].cmp(&b[j]) {
+            std::cmp::Ordering::Less => {
+                out.push(a[i]);
+                i += 1;
+            }
+            std::cmp::Ordering::Greater => {
+                out.push(b[j]);
+                j += 1;
+            }
+            std::cmp::Ordering::Equal => {
+                i += 1;
+                j += 1;
+            }
+        }
+    }
+    out.extend_from_slice(&a[i..]);
+    out.extend_from_slice(&b[j..]);
+    out
+}
+
+fn sub_coords(a: &[f64], b: &[f64]) -> Vec<f64> {
+    let mut out = a.to_vec();
+    for (i, v) in b.iter().enumerate().take(out.len()) {
+        out[i] -= v;
+    }
+    out
+}
+
+fn scale_coords(a: &[f64], by: u64) -> Vec<f64> {
+    a.iter().map(|v| v * by as f64).collect()
+}
+
+/// Total `SHIFT_COORDS` applied by one pass over `instrs`.
+fn coord_shift(instrs: &[StimInstr]) -> Vec<f64> {
+    let mut out = Vec::new();
+    for instr in instrs {
+        match instr {
+            StimInstr::Op { name, args, .. } if name == "SHIFT_COORDS" => {
+                out = add_coords(&out, args);
+            }
+            StimInstr::Op { .. } => {}
+            StimInstr::Repeat { count, body } => {
+                out = add_coords(&out, &scale_coords(&coord_shift(body), *count));
+            }
+        }
+    }
+    out
+}
+
+fn add_coords(a: &[f64], b: &[f64]) -> Vec<f64> {
+    let mut out = a.to_vec();
+    if out.len() < b.len() {
+        out.resize(b.len(), 0.0);
+    }
+    for (i, v) in b.iter().enumerate() {
+        out[i] += v;
+    }
+    out
+}
+
+fn blocks_match(a: &[DemInstr], b: &[DemInstr]) -> bool {
+    const TOL: f64 = 1e-9;
+    let close = |x: &[f64], y: &[f64]| {
+        x.len() == y.len() && x.iter().zip(y).all(|(u, v)| (u - v).abs() <= TOL)
+    };
+    a.len() == b.len()
+        && a.iter().zip(b).all(|(x, y)| match (x, y) {
+            (
+                DemInstr::Detector {
+                    coords: c1,
+                    index: i1,
+                },
+                DemInstr::Detector {
+                    coords: c2,
+                    index: i2,
+                },
+            ) => i1 == i2 && close(c1, c2),
+            (
+                DemInstr::ShiftDetectors {
+                    coords: c1,
+                    count: n1,
+                },
+                DemInstr::ShiftDetectors {
+                    coords: c2,
+                    count: n2,
+                },
+            ) => n1 == n2 && close(c1, c2),
+            _ => x == y,
+        })
+}
+
+fn format_symptoms(dets: &[u64], obs: &[u32]) -> String {
+    let mut parts: Vec<String> = dets.iter().map(|d| format!("D{d}")).collect();
+    parts.extend(obs.iter().map(|o| format!("L{o}")));
+    parts.join(" ")
+}
@@ -1,12 +1,27 @@
+use std::io::BufRead;
+use std::marker::PhantomData;
+
 use rand::Rng;
 
+use crate::compiled::{CompiledCircuit, LoopStack, Op};
 use crate::coords::CoordState;
 use crate::ir::{StimInstr, StimTarget};
+use crate::parser::StreamParser;
+use crate::pauli::{Pauli, two_qubit_pauli};
 use crate::recorder::Recorder;
+use crate::seed::{hits, pick};
+use crate::sim::backend::SimulatorBackend;
 use crate::sim::tableau::StabilizerState;
 
-pub struct Executor {
-    instrs: Vec<StimInstr>,
+/// Runs compiled circuits on a [`SimulatorBackend`], the stabilizer tableau
+/// unless another backend is named.
+#[derive(Clone)]
+pub struct Executor<B = StabilizerState> {
+    program: CompiledCircuit,
+    sweep_bits: Vec<bool>,
+    postselect_measurements: Vec<u64>,
+    postselect_detectors: Vec<u64>,
+    backend: PhantomData<fn() -> B>,
 }
 
 pub struct ExecOutput {
@@ -17,150 +32,223 @@ pub struct ExecOutput {
     pub qubit_coords: std::collections::HashMap<u32, Vec<f64>>,
 }
 
+impl ExecOutput {
+    /// XORs the `OBSERVABLE_INCLUDE` results into one flip bit per observable.
+    pub fn observable_flips(&self, num_observables: usize) -> Vec<bool> {
+        let mut out = vec![false; num_observables];
+        for &(index, bit) in &self.observables {
+            let index = index as usize;
+            if index >= out.len() {
+                out.resize(index + 1, false);
+            }
+            out[index] ^= bit;
+        }
+        out
+    }
+}
+
 impl Executor {
+    /// Compiles `instrs`; unsupported instructions and malformed targets are
+    /// reported here rather than during `run`.
     pub fn from_instrs(instrs: Vec<StimInstr>) -> Result<Self, String> {
-        Ok(Self { instrs })
+        Self::compile(instrs)
+    }
+
+    pub fn from_program(program: CompiledCircuit) -> Self {
+        Self::with_program(program)
+    }
+
+    /// Builds an executor from a circuit read incrementally. Each top-level
+    /// instruction is compiled as soon as it is parsed, so neither the text
+    /// nor the parsed circuit is held in memory as a whole.
+    pub fn from_reader(reader: impl BufRead) -> Result<Self, String> {
+        Ok(Self::with_program(CompiledCircuit::from_stream(
+            StreamParser::new(reader),
+        )?))
     }
+}
 
+impl<B: SimulatorBackend> Executor<B> {
+    /// [`from_instrs`](Executor::from_instrs) for any backend, also checking
+    /// the circuit fits it.
+    pub fn compile(instrs: Vec<StimInstr>) -> Result<Self, String> {
+        let ex = Self::with_program(CompiledCircuit::compile(&instrs)?);
+        ex.check_size(ex.program.num_qubits())?;
+        Ok(ex)
+    }
+
+    pub fn with_program(program: CompiledCircuit) -> Self {
+        Self {
+            program,
+            sweep_bits: Vec::new(),
+            postselect_measurements: Vec::new(),
+            postselect_detectors: Vec::new(),
+            backend: PhantomData,
+        }
+    }
+
+    /// Sets the classical bits read by `sweep[k]` controls. Missing bits are
+    /// treated as zero.
+    pub fn set_sweep_bits(&mut self, bits: Vec<bool>) {
+        self.sweep_bits = bits;
+    }
+
+    /// Sets the measurement and detector indices that
+    /// [`run_postselected`](Self::run_postselected) requires to be zero.
+    pub fn set_postselection(
+        &mut self,
+        measurements: Vec<u64>,
+        detectors: Vec<u64>,
+    ) -> Result<(), String> {
+        if let Some(m) = measurements
+            .iter()
+            .find(|&&m| m >= self.program.num_measurements())
+        {
+            return Err(format!("postselected measurement {m} out of range"));
+        }
+        if let Some(d) = detectors
+            .iter()
+            .find(|&&d| d >= self.program.num_detectors())
+        {
+            return Err(format!("postselected detector {d} out of range"));
+        }
+        self.postselect_measurements = measurements;
+        self.postselect_detectors = detectors;
+        Ok(())
+    }
+
+    pub fn program(&self) -> &CompiledCircuit {
+        &self.program
+    }
+
+    /// Runs one shot from the all-zero state, sized up front to every qubit
+    /// the compiled circuit uses.
     pub fn run(&mut self, rng: &mut impl Rng) -> Result<ExecOutput, String> {
-        let n = max_qubit(&self.instrs)?;
-        let mut state = StabilizerState::new(n);
+        self.check_size(self.program.num_qubits())?;
+        let mut state = B::with_qubits(self.program.num_qubits());
+        self.run_on(&mut state, rng)
+    }
+
+    /// Runs one shot and keeps it only if every post-selected measurement
+    /// and detector is zero; rejected shots return `None`. Shots are
+    /// rejected rather than forced, so kept shots follow the conditional
+    /// distribution exactly.
+    pub fn run_postselected(&mut self, rng: &mut impl Rng) -> Result<Option<ExecOutput>, String> {
+        let out = self.run(rng)?;
+        let fired = self
+            .postselect_measurements
+            .iter()
+            .any(|&m| out.measurements[m as usize])
+            || self
+                .postselect_detectors
+                .iter()
+                .any(|&d| out.detectors[d as usize]);
+        Ok(if fired { None } else { Some(out) })
+    }
+
+    /// Runs one shot on an existing state, which is left as the circuit
+    /// leaves it. The state grows as ops first touch qubits past its end, so
+    /// circuits can be fed to one state piece by piece.
+    pub fn run_on(&mut self, state: &mut B, rng: &mut impl Rng) -> Result<ExecOutput, String> {
+        self.check_size(self.program.num_qubits())?;
         let mut recorder = Recorder::default();
         let mut detectors = Vec::new();
         let mut detector_coords = Vec::new();
         let mut observables = Vec::new();
         let mut coords = CoordState::default();
+        let mut loops = LoopStack::default();
 
-        for instr in &self.instrs {
-            match instr {
-                StimInstr::Op { name, args, targets, .. } => {
-                    match name.as_str() {
-                        "H" => for_each_qubit(targets, |q| state.h(q))?,
-                        "S" => for_each_qubit(targets, |q| state.s(q))?,
-                        "X" => for_each_qubit(targets, |q| state.x_gate(q))?,
-                        "Y" => for_each_qubit(targets, |q| state.y_gate(q))?,
-                        "Z" => for_each_qubit(targets, |q| state.z_gate(q))?,
-                        "CX" | "CNOT" => {
-                            let pairs = qubit_pairs(targets)?;
-                            for (c, t) in pairs {
-                                state.cx(c, t);
-                            }
-                        }
-                        "CZ" => {
-                            let pairs = qubit_pairs(targets)?;
-                            for (c, t) in pairs {
-                                state.cz(c, t);
-                            }
-                        }
-                        "M" => {
-                            for (q, inv) in qubits_with_inversion(targets)? {
-                                let (bit, _) = state.measure_z(q, rng);
-                                let b = (bit == 1) ^ inv;
-                                recorder.push(b);
-                            }
-                        }
-                        "MX" => {
-                            for (q, inv) in qubits_with_inversion(targets)? {
-                                state.h(q);
-                                let (bit, _) = state.measure_z(q, rng);
-                                state.h(q);
-                                let b = (bit == 1) ^ inv;
-                                recorder.push(b);
-                            }
-                        }
-                        "MY" => {
-                            for (q, inv) in qubits_with_inversion(targets)? {
-                                state.s_dag(q);
-                                state.h(q);
-                                let (bit, _) = state.measure_z(q, rng);
-                                state.h(q);
-                                state.s(q);
-                                let b = (bit == 1) ^ inv;
-                                recorder.push(b);
-                            }
-                        }
-                        "X_ERROR" => {
-                            let p = args.get(0).copied().unwrap_or(0.0);
-                            for q in qubits(targets)? {
-                                if rng.r#gen::<f64>() < p {
-                                    state.x_gate(q);
-                                }
-                            }
-                        }
-                        "Z_ERROR" => {
-                            let p = args.get(0).copied().unwrap_or(0.0);
-                            for q in qubits(targets)? {
-                                if rng.r#gen::<f64>() < p {
-                                    state.z_gate(q);
-                                }
-                            }
-                        }
-                        "DEPOLARIZE1" => {
-                            let p = args.get(0).copied().unwrap_or(0.0);
-                            for q in qubits(targets)? {
-                                if rng.r#gen::<f64>() < p {
-                                    match rng.gen_range(0..3) {
-                                        0 => state.x_gate(q),
-                                        1 => state.y_gate(q),
-                                        _ => state.z_gate(q),
-                                    }
-                                }
-                            }
-                        }
-                        "DEPOLARIZE2" => {
-                            let p = args.get(0).copied().unwrap_or(0.0);
-                            let pairs = qubit_pairs(targets)?;
-                            for (a, b) in pairs {
-                                if rng.r#gen::<f64>() < p {
-                                    let r = rng.gen_range(0..15);
-                                    let (pa, pb) = two_qubit_pauli(r);
-                                    apply_pauli(&mut state, a, pa);
-                                    apply_pauli(&mut state, b, pb);
-                                }
+        let ops = self.program.ops();
+        let mut pc = 0;
+        while pc < ops.len() {
+            if let Some(next) = loops.jump(ops, pc) {
+                pc = next;
+                continue;
+            }
+            let width = self.program.width(pc);
+            if width > state.num_qubits() {
+                state.ensure_qubits(width)?;
+            }
+            match &ops[pc] {
+                Op::H(qs) => qs.iter().for_each(|&q| state.h(q)),
+                Op::S(qs) => qs.iter().for_each(|&q| state.s(q)),
+                Op::SDag(qs) => qs.iter().for_each(|&q| state.s_dag(q)),
+                Op::X(qs) => qs.iter().for_each(|&q| state.apply_pauli(q, Pauli::X)),
+                Op::Y(qs) => qs.iter().for_each(|&q| state.apply_pauli(q, Pauli::Y)),
+                Op::Z(qs) => qs.iter().for_each(|&q| state.apply_pauli(q, Pauli::Z)),
+                Op::Cx(pairs) => {
+                    for &(c, t) in pairs {
+                        match c {
+                            Control::Qubit(c) => state.cx(c, t),
+                            Control::Sweep(k) if self.sweep_bit(k) => {
+                                state.apply_pauli(t, Pauli::X)
                             }
+                            Control::Sweep(_) => {}
                         }
-                        "QUBIT_COORDS" => {
-                            let coords_vec = coords.apply_offset(args);
-                            for t in targets {
-                                if let StimTarget::Qubit(q) = t {
-                                    coords.qubit_coords.insert(*q, coords_vec.clone());
-                                } else {
-                                    return Err("QUBIT_COORDS expects qubit targets".to_string());
-                                }
+                    }
+                }
+                Op::Cz(pairs) => {
+                    for &(c, t) in pairs {
+                        match c {
+                            Control::Qubit(c) => state.cz(c, t),
+                            Control::Sweep(k) if self.sweep_bit(k) => {
+                                state.apply_pauli(t, Pauli::Z)
                             }
+                            Control::Sweep(_) => {}
                         }
-                        "SHIFT_COORDS" => {
-                            coords.shift(args);
-                        }
-                        "TICK" => {
-                            coords.tick += 1;
-                        }
-                        "DETECTOR" => {
-                            let bit = xor_recs(&recorder, targets)?;
-                            detectors.push(bit);
-                            let det_coords = coords.apply_offset(args);
-                            detector_coords.push(det_coords);
-                        }
-                        "OBSERVABLE_INCLUDE" => {
-                            let index = args.get(0).copied().unwrap_or(0.0) as u32;
-                            let bit = xor_recs(&recorder, targets)?;
-                            observables.push((index, bit));
-                        }
-                        _ => return Err(format!("unsupported instruction {}", name)),
                     }
                 }
-                StimInstr::Repeat { count, body } => {
-                    for _ in 0..*count {
-                        let mut inner = Executor::from_instrs(body.clone())?;
-                        let out = inner.run(rng)?;
-                        recorder.extend(out.measurements);
-                        detectors.extend(out.detectors);
-                        detector_coords.extend(out.detector_coords);
-                        observables.extend(out.observables);
-                        coords.qubit_coords.extend(out.qubit_coords);
+                Op::Measure { basis, targets } => {
+                    for &(q, inv) in targets {
+                        recorder.push(state.measure(*basis, q, rng) ^ inv);
                     }
                 }
+                Op::Reset { basis, targets } => {
+                    for &q in targets {
+                        state.reset(*basis, q, rng);
+                    }
+                }
+                Op::XError { p, targets } => {
+                    for i in hits(rng, *p, targets.len()) {
+                        state.apply_pauli(targets[i], Pauli::X);
+                    }
+                }
+                Op::ZError { p, targets } => {
+                    for i in hits(rng, *p, targets.len()) {
+                        state.apply_pauli(targets[i], Pauli::Z);
+                    }
+                }
+                Op::Depolarize1 { p, targets } => {
+                    for i in hits(rng, *p, targets.len()) {
+                        state.apply_pauli(targets[i], Pauli::NON_IDENTITY[pick(rng, 3)]);
+                    }
+                }
+                Op::Depolarize2 { p, pairs } => {
+                    for i in hits(rng, *p, pairs.len()) {
+                        let (a, b) = pairs[i];
+                        let (pa, pb) = two_qubit_pauli(pick(rng, 15));
+                        state.apply_pauli(a, pa);
+                        state.apply_pauli(b, pb);
+                    }
+                }
+                Op::QubitCoords { coords: c, qubits } => {
+                    let coords_vec = coords.apply_offset(c);
+                    for &q in qubits {
+                        coords.qubit_coords.insert(q, coords_vec.clone());
+                    }
+                }
+                Op::ShiftCoords(delta) => coords.shift(delta),
+                Op::Tick => coords.tick += 1,
+                Op::Detector { coords: c, recs } => {
+                    detectors.push(xor_offsets(&recorder, recs));
+                    detector_coords.push(coords.apply_offset(c));
+                }
+                Op::ObservableInclude { index, recs } => {
+                    observables.push((*index, xor_offsets(&recorder, recs)));
+                }
+                Op::Repeat { .. } | Op::EndRepeat { .. } => unreachable!("handled by LoopStack"),
             }
+            pc += 1;
         }
 
         Ok(ExecOutput {
@@ -173,6 +261,29 @@ impl Executor {
     }
 }
 
+impl<B: SimulatorBackend> Executor<B> {
+    fn sweep_bit(&self, k: u32) -> bool {
+        self.sweep_bits.get(k as usize).copied().unwrap_or(false)
+    }
+
+    fn check_size(&self, n: usize) -> Result<(), String> {
+        if n > B::MAX_QUBITS {
+            return Err(format!(
+                "{n} qubits is too many for this backend (max {})",
+                B::MAX_QUBITS
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// XORs record offsets already validated at compile time.
+fn xor_offsets(r: &Recorder, recs: &[i32]) -> bool {
+    recs.iter().fold(false, |acc, &o| {
+        acc ^ r.rec(o).expect("rec checked at compile time")
+    })
+}
+
 fn recorder_bits(r: Recorder) -> Vec<bool> {
     let mut out = Vec::new();
     for i in 1..=r.len() {
@@ -182,29 +293,7 @@ fn recorder_bits(r: Recorder) -> Vec<bool> {
     out
 }
 
-fn max_qubit(instrs: &[StimInstr]) -> Result<usize, String> {
-    let mut max_q: Option<u32> = None;
-    for i in instrs {
-        match i {
-            StimInstr::Op { targets, .. } => {
-                for t in targets {
-                    if let StimTarget::Qubit(q) = t {
-                        max_q = Some(max_q.map_or(*q, |m| m.max(*q)));
-                    } else if let StimTarget::QubitInv(q) = t {
-                        max_q = Some(max_q.map_or(*q, |m| m.max(*q)));
-                    }
-                }
-            }
-            StimInstr::Repeat { body, .. } => {
-                let inner = max_qubit(body)? as u32;
-                max_q = Some(max_q.map_or(inner, |m| m.max(inner)));
-            }
-        }
-    }
-    Ok(max_q.map(|m| (m as usize) + 1).unwrap_or(0))
-}
-
-fn qubits(targets: &[StimTarget]) -> Result<Vec<usize>, String> {
+pub(crate) fn qubits(targets: &[StimTarget]) -> Result<Vec<usize>, String> {
     let mut out = Vec::new();
     for t in targets {
         out.push(expect_qubit(t)?);
@@ -212,7 +301,7 @@ fn qubits(targets: &[StimTarget]) -> Result<Vec<usize>, String> {
     Ok(out)
 }
 
-fn qubits_with_inversion(targets: &[StimTarget]) -> Result<Vec<(usize, bool)>, String> {
+pub(crate) fn qubits_with_inversion(targets: &[StimTarget]) -> Result<Vec<(usize, bool)>, String> {
     let mut out = Vec::new();
     for t in targets {
         match t {
@@ -224,13 +313,6 @@ fn qubits_with_inversion(targets: &[StimTarget]) -> Result<Vec<(usize, bool)>, S
     Ok(out)
 }
 
-fn for_each_qubit<F: FnMut(usize)>(targets: &[StimTarget], mut f: F) -> Result<(), String> {
-    for t in targets {
-        f(expect_qubit(t)?);
-    }
-    Ok(())
-}
-
 fn expect_qubit(t: &StimTarget) -> Result<usize, String> {
     match t {
         StimTarget::Qubit(q) => Ok(*q as usize),
@@ -239,55 +321,45 @@ fn expect_qubit(t: &StimTarget) -> Result<usize, String> {
     }
 }
 
-fn qubit_pairs(targets: &[StimTarget]) -> Result<Vec<(usize, usize)>, String> {
-    if targets.len() % 2 != 0 {
+/// Control of a two-qubit controlled gate: a qubit, or a classical sweep bit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Control {
+    Qubit(usize),
+    Sweep(u32),
+}
+
+pub(crate) fn controlled_pairs(targets: &[StimTarget]) -> Result<Vec<(Control, usize)>, String> {
+    if !targets.len().is_multiple_of(2) {
         return Err("odd number of targets".to_string());
     }
     let mut out = Vec::new();
     let mut it = targets.iter();
     while let (Some(a), Some(b)) = (it.next(), it.next()) {
-        out.push((expect_qubit(a)?, expect_qubit(b)?));
-    }
-    Ok(out)
-}
-
-fn xor_recs(r: &Recorder, targets: &[StimTarget]) -> Result<bool, String> {
-    let mut acc = false;
-    for t in targets {
-        match t {
-            StimTarget::Rec(o) => {
-                let bit = r.rec(*o).ok_or("rec out of range")?;
-                acc ^= bit;
-            }
-            _ => return Err("detector target must be rec".to_string()),
+        let control = match a {
+            StimTarget::Sweep(k) => Control::Sweep(*k),
+            _ => Control::Qubit(expect_qubit(a)?),
+        };
+        let target = expect_qubit(b)?;
+        if control == Control::Qubit(target) {
+            return Err(format!("qubit {target} repeated within a target pair"));
         }
+        out.push((control, target));
     }
-    Ok(acc)
+    Ok(out)
 }
 
-fn apply_pauli(state: &mut StabilizerState, q: usize, p: u8) {
-    match p {
-        0 => {}
-        1 => state.x_gate(q),
-        2 => state.y_gate(q),
-        3 => state.z_gate(q),
-        _ => {}
+pub(crate) fn qubit_pairs(targets: &[StimTarget]) -> Result<Vec<(usize, usize)>, String> {
+    if !targets.len().is_multiple_of(2) {
+        return Err("odd number of targets".to_string());
     }
-}
-
-fn two_qubit_pauli(r: usize) -> (u8, u8) {
-    // Map 0..14 to 15 non-identity pairs from {I,X,Y,Z}^2 \ {II}
-    let mut idx = 0usize;
-    for a in 0..4 {
-        for b in 0..4 {
-            if a == 0 && b == 0 {
-                continue;
-            }
-            if idx == r {
-                return (a as u8, b as u8);
-            }
-            idx += 1;
+    let mut out = Vec::new();
+    let mut it = targets.iter();
+    while let (Some(a), Some(b)) = (it.next(), it.next()) {
+        let (a, b) = (expect_qubit(a)?, expect_qubit(b)?);
+        if a == b {
+            return Err(format!("qubit {a} repeated within a target pair"));
         }
+        out.push((a, b));
     }
-    (0, 0)
+    Ok(out)
 }
@@ -0,0 +1,42 @@
+/// Instructions that append one bit per target to the measurement record.
+pub fn is_measurement(name: &str) -> bool {
+    matches!(name, "M" | "MX" | "MY")
+}
+
+/// Whether adjacent instructions of this gate with identical arguments can be
+/// merged into one by concatenating their targets. Only gates known to act
+/// target by target qualify; annotations whose meaning depends on the
+/// instruction boundary (one detector per `DETECTOR`, one tick per `TICK`,
+/// ...) and gates not listed here never fuse.
+pub fn is_fusable(name: &str) -> bool {
+    matches!(
+        name,
+        "H" | "S"
+            | "S_DAG"
+            | "X"
+            | "Y"
+            | "Z"
+            | "CX"
+            | "CNOT"
+            | "CZ"
+            | "R"
+            | "RX"
+            | "RY"
+            | "RZ"
+            | "QUBIT_COORDS"
+    ) || is_measurement(name)
+        || is_noise(name)
+}
+
+/// Instructions that only carry metadata or read the measurement record.
+pub fn is_annotation(name: &str) -> bool {
+    matches!(
+        name,
+        "DETECTOR" | "OBSERVABLE_INCLUDE" | "QUBIT_COORDS" | "SHIFT_COORDS" | "TICK"
+    )
+}
+
+/// Pauli noise channels.
+pub fn is_noise(name: &str) -> bool {
+    matches!(name, "X_ERROR" | "Z_ERROR" | "DEPOLARIZE1" | "DEPOLARIZE2")
+}
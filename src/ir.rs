@@ -3,6 +3,7 @@ pub enum StimTarget {
     Qubit(u32),
     QubitInv(u32),
     Rec(i32),
+    Sweep(u32),
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -0,0 +1,164 @@
+use std::io::BufRead;
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum TokenKind {
+    /// A name, number or target. Bracketed suffixes such as `rec[-1]` or an
+    /// instruction tag `H[tag]` are part of the word.
+    Word(String),
+    LParen,
+    RParen,
+    Comma,
+    LBrace,
+    RBrace,
+    Newline,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Token {
+    pub kind: TokenKind,
+    pub line: usize,
+    pub col: usize,
+}
+
+impl Token {
+    pub fn describe(&self) -> String {
+        let text = match &self.kind {
+            TokenKind::Word(w) => w.as_str(),
+            TokenKind::LParen => "(",
+            TokenKind::RParen => ")",
+            TokenKind::Comma => ",",
+            TokenKind::LBrace => "{",
+            TokenKind::RBrace => "}",
+            TokenKind::Newline => "end of line",
+        };
+        format!("line {} col {}: `{}`", self.line, self.col, text)
+    }
+}
+
+/// Splits Stim source into tokens, reading one line at a time. Comments
+/// (`#` to end of line) are dropped and every line ends in a `Newline`.
+pub(crate) struct Lexer<R: BufRead> {
+    reader: R,
+    line: Vec<char>,
+    pos: usize,
+    line_done: bool,
+    line_no: usize,
+    peeked: Option<Token>,
+}
+
+impl<R: BufRead> Lexer<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            line: Vec::new(),
+            pos: 0,
+            line_done: true,
+            line_no: 0,
+            peeked: None,
+        }
+    }
+
+    pub fn line_no(&self) -> usize {
+        self.line_no
+    }
+
+    pub fn peek_kind(&mut self) -> Result<Option<&TokenKind>, String> {
+        if self.peeked.is_none() {
+            self.peeked = self.lex()?;
+        }
+        Ok(self.peeked.as_ref().map(|t| &t.kind))
+    }
+
+    pub fn next_token(&mut self) -> Result<Option<Token>, String> {
+        match self.peeked.take() {
+            Some(t) => Ok(Some(t)),
+            None => self.lex(),
+        }
+    }
+
+    fn lex(&mut self) -> Result<Option<Token>, String> {
+        loop {
+            if self.line_done && !self.read_line()? {
+                return Ok(None);
+            }
+            let col = self.pos + 1;
+            let Some(&c) = self.line.get(self.pos) else {
+                self.line_done = true;
+                return Ok(Some(Token {
+                    kind: TokenKind::Newline,
+                    line: self.line_no,
+                    col,
+                }));
+            };
+            let kind = match c {
+                ' ' | '\t' | '\r' => {
+                    self.pos += 1;
+                    continue;
+                }
+                '#' => {
+                    self.pos = self.line.len();
+                    continue;
+                }
+                '(' => TokenKind::LParen,
+                ')' => TokenKind::RParen,
+                ',' => TokenKind::Comma,
+                '{' => TokenKind::LBrace,
+                '}' => TokenKind::RBrace,
+                _ => {
+                    let word = self.lex_word()?;
+                    return Ok(Some(Token {
+                        kind: TokenKind::Word(word),
+                        line: self.line_no,
+                        col,
+                    }));
+                }
+            };
+            self.pos += 1;
+            return Ok(Some(Token {
+                kind,
+                line: self.line_no,
+                col,
+            }));
+        }
+    }
+
+    fn lex_word(&mut self) -> Result<String, String> {
+        let start = self.pos;
+        while let Some(&c) = self.line.get(self.pos) {
+            match c {
+                ' ' | '\t' | '\r' | '#' | '(' | ')' | ',' | '{' | '}' => break,
+                '[' => {
+                    let close = self.line[self.pos..]
+                        .iter()
+                        .position(|&c| c == ']')
+                        .ok_or_else(|| {
+                            format!(
+                                "line {} col {}: unterminated `[`",
+                                self.line_no,
+                                self.pos + 1
+                            )
+                        })?;
+                    self.pos += close + 1;
+                }
+                _ => self.pos += 1,
+            }
+        }
+        Ok(self.line[start..self.pos].iter().collect())
+    }
+
+    fn read_line(&mut self) -> Result<bool, String> {
+        let mut buf = String::new();
+        let read = self
+            .reader
+            .read_line(&mut buf)
+            .map_err(|e| format!("line {}: {e}", self.line_no + 1))?;
+        if read == 0 {
+            return Ok(false);
+        }
+        self.line_no += 1;
+        self.line = buf.trim_end_matches('\n').chars().collect();
+        self.pos = 0;
+        self.line_done = false;
+        Ok(true)
+    }
+}
@@ -5,6 +5,23 @@ pub fn version() -> &'static str {
 pub mod ir;
 pub mod recorder;
 pub mod parser;
+mod lexer;
 pub mod executor;
+pub mod compiled;
 pub mod sim;
+pub mod pauli;
+pub mod clifford;
 pub mod coords;
+pub mod circuit;
+pub mod gates;
+pub mod stats;
+pub mod normalize;
+pub mod result_format;
+pub mod cli;
+pub mod dem;
+pub mod dem_sampler;
+pub mod error_analyzer;
+pub mod m2d;
+pub mod sampler;
+pub mod seed;
+pub mod yao_backend;
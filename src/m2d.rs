@@ -0,0 +1,162 @@
+use std::collections::{BTreeSet, HashMap};
+
+use crate::compiled::{CompiledCircuit, LoopStack, Op};
+use crate::executor::Control;
+use crate::ir::StimInstr;
+use crate::recorder::Recorder;
+use crate::sim::reference::{compile_noiseless, reference_sample};
+use crate::stats::stats;
+
+/// Converts measurement records into detection events and observable flips.
+///
+/// A detector fires when its parity differs from the parity in a noiseless
+/// reference sample of the circuit, in which every random measurement takes
+/// the value 0. Sweep bits only change the reference, so one is cached for
+/// each configuration of the sweep bits the circuit reads.
+pub struct M2dConverter {
+    noiseless: CompiledCircuit,
+    num_measurements: usize,
+    num_detectors: usize,
+    num_observables: usize,
+    num_sweep_bits: usize,
+    /// The `k` of every `sweep[k]` the circuit reads, ascending.
+    used_sweep_bits: Vec<usize>,
+    /// References keyed by the values of `used_sweep_bits`.
+    references: HashMap<Vec<bool>, Vec<bool>>,
+}
+
+impl M2dConverter {
+    pub fn new(instrs: &[StimInstr]) -> Result<Self, String> {
+        let st = stats(instrs);
+        let noiseless = compile_noiseless(instrs)?;
+        let used_sweep_bits = sweep_bits_read(&noiseless);
+        let mut references = HashMap::new();
+        references.insert(
+            vec![false; used_sweep_bits.len()],
+            reference_sample(&noiseless, &[])?,
+        );
+        Ok(Self {
+            noiseless,
+            num_measurements: st.num_measurements as usize,
+            num_detectors: st.num_detectors as usize,
+            num_observables: st.num_observables as usize,
+            num_sweep_bits: st.num_sweep_bits,
+            used_sweep_bits,
+            references,
+        })
+    }
+
+    pub fn num_measurements(&self) -> usize {
+        self.num_measurements
+    }
+
+    pub fn num_detectors(&self) -> usize {
+        self.num_detectors
+    }
+
+    pub fn num_observables(&self) -> usize {
+        self.num_observables
+    }
+
+    pub fn num_sweep_bits(&self) -> usize {
+        self.num_sweep_bits
+    }
+
+    /// Returns `(detectors, observables)` for one shot of measurements.
+    /// Missing sweep bits are treated as zero; bits past the circuit's last
+    /// `sweep[k]` are an error.
+    pub fn convert(
+        &mut self,
+        measurements: &[bool],
+        sweep_bits: &[bool],
+    ) -> Result<(Vec<bool>, Vec<bool>), String> {
+        if measurements.len() != self.num_measurements {
+            return Err(format!(
+                "got {} measurements, circuit has {}",
+                measurements.len(),
+                self.num_measurements
+            ));
+        }
+        if sweep_bits.len() > self.num_sweep_bits {
+            return Err(format!(
+                "got {} sweep bits, circuit has {}",
+                sweep_bits.len(),
+                self.num_sweep_bits
+            ));
+        }
+        let key: Vec<bool> = self
+            .used_sweep_bits
+            .iter()
+            .map(|&k| sweep_bits.get(k).copied().unwrap_or(false))
+            .collect();
+        if !self.references.contains_key(&key) {
+            let reference = reference_sample(&self.noiseless, sweep_bits)?;
+            self.references.insert(key.clone(), reference);
+        }
+        let reference = &self.references[&key];
+        let diff = measurements.iter().zip(reference).map(|(a, b)| a ^ b);
+        Ok(replay(
+            &self.noiseless,
+            diff,
+            self.num_detectors,
+            self.num_observables,
+        ))
+    }
+}
+
+/// The `k` of every `sweep[k]` control in `program`, ascending.
+fn sweep_bits_read(program: &CompiledCircuit) -> Vec<usize> {
+    let mut out = BTreeSet::new();
+    for op in program.ops() {
+        if let Op::Cx(pairs) | Op::Cz(pairs) = op {
+            for &(c, _) in pairs {
+                if let Control::Sweep(k) = c {
+                    out.insert(k as usize);
+                }
+            }
+        }
+    }
+    out.into_iter().collect()
+}
+
+/// Walks `program` once, reading measurement flips from `bits`, and returns
+/// the detectors and observables they toggle.
+fn replay(
+    program: &CompiledCircuit,
+    mut bits: impl Iterator<Item = bool>,
+    num_detectors: usize,
+    num_observables: usize,
+) -> (Vec<bool>, Vec<bool>) {
+    let mut recorder = Recorder::default();
+    let mut detectors = Vec::with_capacity(num_detectors);
+    let mut observables = vec![false; num_observables];
+    // Offsets were checked against the record when `program` was compiled.
+    let parity = |recorder: &Recorder, recs: &[i32]| {
+        recs.iter().fold(false, |acc, &o| {
+            acc ^ recorder.rec(o).expect("rec checked at compile time")
+        })
+    };
+    let mut loops = LoopStack::default();
+    let ops = program.ops();
+    let mut pc = 0;
+    while pc < ops.len() {
+        if let Some(next) = loops.jump(ops, pc) {
+            pc = next;
+            continue;
+        }
+        match &ops[pc] {
+            Op::Measure { targets, .. } => {
+                for _ in targets {
+                    recorder.push(bits.next().expect("measurement count checked"));
+                }
+            }
+            Op::Detector { recs, .. } => detectors.push(parity(&recorder, recs)),
+            Op::ObservableInclude { index, recs } => {
+                observables[*index as usize] ^= parity(&recorder, recs);
+            }
+            _ => {}
+        }
+        pc += 1;
+    }
+    (detectors, observables)
+}
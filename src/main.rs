@@ -1,3 +1,7 @@
 fn main() {
-    println!("rstim {}", rstim::version());
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Err(e) = rstim::cli::run(&args) {
+        eprintln!("{e}");
+        std::process::exit(1);
+    }
 }
@@ -0,0 +1,140 @@
+use std::collections::HashMap;
+
+use crate::circuit::try_fuse;
+use crate::gates::{is_annotation, is_fusable, is_measurement};
+use crate::ir::{StimInstr, StimTarget};
+
+/// Rewrites a program into an equivalent one with fewer instructions.
+///
+/// - Gates without targets and empty `REPEAT` blocks are dropped.
+/// - An instruction is fused into an earlier one with the same gate, tag and
+///   arguments when their qubits are disjoint, so each fused instruction still
+///   acts on every qubit at most once.
+/// - Within a `TICK`-free region, unitary gates and noise channels may move
+///   back past other such instructions on disjoint qubits to reach a fusion
+///   partner. Measurements and annotations are never reordered.
+pub fn normalize(instrs: Vec<StimInstr>) -> Vec<StimInstr> {
+    let mut fuser = Fuser::default();
+    for instr in instrs {
+        match instr {
+            StimInstr::Repeat { count, body } => {
+                let body = normalize(body);
+                if !body.is_empty() {
+                    fuser.push(StimInstr::Repeat { count, body });
+                }
+            }
+            StimInstr::Op {
+                ref name,
+                ref targets,
+                ..
+            } => {
+                if is_fusable(name) && targets.is_empty() {
+                    continue;
+                }
+                if !fuser.fuse_backwards(&instr) {
+                    fuser.push(instr);
+                }
+            }
+        }
+    }
+    fuser.out
+}
+
+/// Name, tag and argument bits: instructions can only fuse on equal keys.
+type FuseKey = (String, Option<String>, Vec<u64>);
+
+fn fuse_key(instr: &StimInstr) -> Option<FuseKey> {
+    match instr {
+        StimInstr::Op {
+            name, tag, args, ..
+        } => Some((
+            name.clone(),
+            tag.clone(),
+            args.iter().map(|a| a.to_bits()).collect(),
+        )),
+        StimInstr::Repeat { .. } => None,
+    }
+}
+
+/// The instructions emitted so far, indexed so a fusion partner is found
+/// without rescanning them.
+#[derive(Default)]
+struct Fuser {
+    out: Vec<StimInstr>,
+    /// Index of the last instruction touching each qubit.
+    last_touch: HashMap<u32, usize>,
+    /// Index of the last instruction nothing may move back past.
+    last_fixed: Option<usize>,
+    /// Index of the last instruction with each fusion key.
+    last_of_key: HashMap<FuseKey, usize>,
+}
+
+impl Fuser {
+    fn push(&mut self, instr: StimInstr) {
+        let i = self.out.len();
+        for q in touched_qubits(&instr) {
+            self.last_touch.insert(q, i);
+        }
+        if !is_reorderable(&instr) {
+            self.last_fixed = Some(i);
+        }
+        if let Some(key) = fuse_key(&instr) {
+            self.last_of_key.insert(key, i);
+        }
+        self.out.push(instr);
+    }
+
+    /// Fuses `instr` into the latest earlier instruction it can reach: one
+    /// it could move back past everything after, stopping at the first
+    /// instruction on a shared qubit or, unless both are reorderable, at the
+    /// one just before it.
+    fn fuse_backwards(&mut self, instr: &StimInstr) -> bool {
+        let Some(last) = self.out.len().checked_sub(1) else {
+            return false;
+        };
+        let mut barrier = if is_reorderable(instr) {
+            self.last_fixed
+        } else {
+            Some(last)
+        };
+        for q in touched_qubits(instr) {
+            barrier = barrier.max(self.last_touch.get(&q).copied());
+        }
+        let Some(&i) = fuse_key(instr).and_then(|key| self.last_of_key.get(&key)) else {
+            return false;
+        };
+        if barrier.is_some_and(|b| i < b) {
+            return false;
+        }
+        // Nothing after the barrier touches these qubits, so `out[i]` shares
+        // one exactly when it is the last to touch it.
+        if touched_qubits(instr).any(|q| self.last_touch.get(&q) == Some(&i)) {
+            return false;
+        }
+        if !try_fuse(&mut self.out[i], instr) {
+            return false;
+        }
+        for q in touched_qubits(instr) {
+            self.last_touch.insert(q, i);
+        }
+        true
+    }
+}
+
+fn is_reorderable(instr: &StimInstr) -> bool {
+    match instr.name() {
+        Some(name) => !is_measurement(name) && !is_annotation(name),
+        None => false,
+    }
+}
+
+fn touched_qubits(instr: &StimInstr) -> impl Iterator<Item = u32> + '_ {
+    instr
+        .targets()
+        .unwrap_or(&[])
+        .iter()
+        .filter_map(|t| match t {
+            StimTarget::Qubit(q) | StimTarget::QubitInv(q) => Some(*q),
+            _ => None,
+        })
+}
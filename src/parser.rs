@@ -1,76 +1,202 @@
+use std::io::BufRead;
+
 use crate::ir::{StimInstr, StimTarget};
+use crate::lexer::{Lexer, Token, TokenKind};
 
 pub fn parse_lines(input: &str) -> Result<Vec<StimInstr>, String> {
-    let mut stack: Vec<Vec<StimInstr>> = vec![Vec::new()];
-    let mut repeat_counts: Vec<u64> = Vec::new();
+    StreamParser::new(input.as_bytes()).collect()
+}
+
+/// Parses instructions lazily from any `BufRead`.
+///
+/// Top-level instructions are yielded as soon as they have been read; a
+/// `REPEAT` block is yielded as a single nested `StimInstr::Repeat` once its
+/// closing `}` has been read.
+pub struct StreamParser<R: BufRead> {
+    lexer: Lexer<R>,
+    failed: bool,
+}
+
+enum Stmt {
+    Close,
+    Open(u64),
+    Instr(StimInstr),
+}
 
-    for (line_no, raw) in input.lines().enumerate() {
-        let line = raw.split('#').next().unwrap().trim();
-        if line.is_empty() {
-            continue;
+impl<R: BufRead> StreamParser<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            lexer: Lexer::new(reader),
+            failed: false,
         }
-        if line == "}" {
-            let body = stack.pop().ok_or_else(|| format!("line {}: unmatched }}", line_no + 1))?;
-            let count = repeat_counts.pop().ok_or_else(|| format!("line {}: unmatched }}", line_no + 1))?;
-            if count == 0 {
-                return Err(format!("line {}: REPEAT 0 not allowed", line_no + 1));
+    }
+
+    fn read_block(&mut self) -> Result<Vec<StimInstr>, String> {
+        let mut body = Vec::new();
+        loop {
+            match self.next_stmt()? {
+                None => return Err("unterminated REPEAT block".to_string()),
+                Some(Stmt::Close) => return Ok(body),
+                Some(Stmt::Open(count)) => {
+                    let inner = self.read_block()?;
+                    body.push(StimInstr::Repeat { count, body: inner });
+                }
+                Some(Stmt::Instr(instr)) => body.push(instr),
             }
-            let repeat = StimInstr::Repeat { count, body };
-            stack
-                .last_mut()
-                .ok_or_else(|| format!("line {}: repeat outside program", line_no + 1))?
-                .push(repeat);
-            continue;
         }
+    }
 
-        let mut is_block_start = false;
-        let mut line = line.to_string();
-        if line.ends_with('{') {
-            is_block_start = true;
-            line = line[..line.len() - 1].trim().to_string();
+    fn next_instr(&mut self) -> Result<Option<StimInstr>, String> {
+        match self.next_stmt()? {
+            None => Ok(None),
+            Some(Stmt::Close) => Err(format!("line {}: unmatched }}", self.lexer.line_no())),
+            Some(Stmt::Open(count)) => {
+                let body = self.read_block()?;
+                Ok(Some(StimInstr::Repeat { count, body }))
+            }
+            Some(Stmt::Instr(instr)) => Ok(Some(instr)),
         }
+    }
 
-        let mut parts = line.split_whitespace();
-        let name_token = parts
-            .next()
-            .ok_or_else(|| format!("line {}: empty", line_no + 1))?;
-        let (name, args) = split_name_and_args(name_token)?;
+    fn next_stmt(&mut self) -> Result<Option<Stmt>, String> {
+        self.skip_newlines()?;
+        let Some(token) = self.lexer.next_token()? else {
+            return Ok(None);
+        };
+        let word = match &token.kind {
+            TokenKind::RBrace => {
+                self.expect_end_of_line()?;
+                return Ok(Some(Stmt::Close));
+            }
+            TokenKind::Word(w) => w.clone(),
+            _ => return Err(format!("{}: expected instruction name", token.describe())),
+        };
+        let (name, tag) =
+            split_name_and_tag(&word).map_err(|e| format!("{}: {e}", token.describe()))?;
         let name = name.to_ascii_uppercase();
+        let args = self.parse_args()?;
 
-        if is_block_start {
-            if name != "REPEAT" {
-                return Err(format!("line {}: only REPEAT opens a block", line_no + 1));
+        if name == "REPEAT" {
+            if tag.is_some() {
+                return Err(format!("{}: REPEAT does not take a tag", token.describe()));
+            }
+            if !args.is_empty() {
+                return Err(format!("{}: REPEAT takes no arguments", token.describe()));
+            }
+            let count_token = self
+                .lexer
+                .next_token()?
+                .ok_or_else(|| format!("line {}: missing repeat count", self.lexer.line_no()))?;
+            let count: u64 = match &count_token.kind {
+                TokenKind::Word(w) => w.parse().ok(),
+                _ => None,
+            }
+            .ok_or_else(|| format!("{}: bad repeat count", count_token.describe()))?;
+            if count == 0 {
+                return Err(format!("{}: REPEAT 0 not allowed", count_token.describe()));
+            }
+            self.skip_newlines()?;
+            match self.lexer.next_token()? {
+                Some(Token {
+                    kind: TokenKind::LBrace,
+                    ..
+                }) => {
+                    self.expect_end_of_line()?;
+                    return Ok(Some(Stmt::Open(count)));
+                }
+                Some(t) => return Err(format!("{}: expected {{", t.describe())),
+                None => return Err("unterminated REPEAT block".to_string()),
             }
-            let count_token = parts
-                .next()
-                .ok_or_else(|| format!("line {}: missing repeat count", line_no + 1))?;
-            let count: u64 = count_token
-                .parse()
-                .map_err(|_| format!("line {}: bad repeat count", line_no + 1))?;
-            repeat_counts.push(count);
-            stack.push(Vec::new());
-            continue;
         }
 
         let mut instr = StimInstr::new(&name, args, vec![]);
-        if let StimInstr::Op { targets, .. } = &mut instr {
-            for token in parts {
-                if let Some(t) = parse_target(token)? {
-                    targets.push(t);
+        if let StimInstr::Op {
+            tag: instr_tag,
+            targets,
+            ..
+        } = &mut instr
+        {
+            *instr_tag = tag;
+            while let Some(token) = self.lexer.next_token()? {
+                match &token.kind {
+                    TokenKind::Newline => break,
+                    TokenKind::Word(w) => {
+                        if let Some(t) =
+                            parse_target(w).map_err(|e| format!("{}: {e}", token.describe()))?
+                        {
+                            targets.push(t);
+                        }
+                    }
+                    TokenKind::LBrace => {
+                        return Err(format!("{}: only REPEAT opens a block", token.describe()));
+                    }
+                    _ => return Err(format!("{}: unexpected token", token.describe())),
+                }
+            }
+        }
+        Ok(Some(Stmt::Instr(instr)))
+    }
+
+    fn parse_args(&mut self) -> Result<Vec<f64>, String> {
+        if !matches!(self.lexer.peek_kind()?, Some(TokenKind::LParen)) {
+            return Ok(vec![]);
+        }
+        self.lexer.next_token()?;
+        let mut args = Vec::new();
+        loop {
+            let token = self.lexer.next_token()?.ok_or_else(|| {
+                format!("line {}: unterminated argument list", self.lexer.line_no())
+            })?;
+            match &token.kind {
+                TokenKind::RParen if args.is_empty() => return Ok(args),
+                TokenKind::Word(w) => {
+                    args.push(parse_arg(w).map_err(|e| format!("{}: {e}", token.describe()))?);
                 }
+                _ => return Err(format!("{}: expected argument", token.describe())),
+            }
+            let token = self.lexer.next_token()?.ok_or_else(|| {
+                format!("line {}: unterminated argument list", self.lexer.line_no())
+            })?;
+            match &token.kind {
+                TokenKind::Comma => {}
+                TokenKind::RParen => return Ok(args),
+                _ => return Err(format!("{}: expected , or )", token.describe())),
             }
         }
-        stack
-            .last_mut()
-            .ok_or_else(|| format!("line {}: instruction outside program", line_no + 1))?
-            .push(instr);
     }
 
-    if stack.len() != 1 {
-        return Err("unterminated REPEAT block".to_string());
+    fn skip_newlines(&mut self) -> Result<(), String> {
+        while matches!(self.lexer.peek_kind()?, Some(TokenKind::Newline)) {
+            self.lexer.next_token()?;
+        }
+        Ok(())
     }
 
-    Ok(stack.pop().unwrap())
+    fn expect_end_of_line(&mut self) -> Result<(), String> {
+        match self.lexer.next_token()? {
+            None
+            | Some(Token {
+                kind: TokenKind::Newline,
+                ..
+            }) => Ok(()),
+            Some(t) => Err(format!("{}: expected end of line", t.describe())),
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for StreamParser<R> {
+    type Item = Result<StimInstr, String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        let item = self.next_instr().transpose();
+        if matches!(item, Some(Err(_))) {
+            self.failed = true;
+        }
+        item
+    }
 }
 
 fn parse_target(token: &str) -> Result<Option<StimTarget>, String> {
@@ -84,6 +210,13 @@ fn parse_target(token: &str) -> Result<Option<StimTarget>, String> {
         }
         return Ok(Some(StimTarget::Rec(val)));
     }
+    if token.starts_with("sweep[") && token.ends_with(']') {
+        let inner = &token[6..token.len() - 1];
+        let val: u32 = inner
+            .parse()
+            .map_err(|_| format!("bad sweep target {token}"))?;
+        return Ok(Some(StimTarget::Sweep(val)));
+    }
     let (negated, raw) = if let Some(rest) = token.strip_prefix('!') {
         (true, rest)
     } else {
@@ -98,23 +231,21 @@ fn parse_target(token: &str) -> Result<Option<StimTarget>, String> {
     Err(format!("unsupported target {token}"))
 }
 
-fn split_name_and_args(token: &str) -> Result<(&str, Vec<f64>), String> {
-    if let Some(idx) = token.find('(') {
-        if !token.ends_with(')') {
-            return Err(format!("bad args {token}"));
-        }
-        let name = &token[..idx];
-        let args_str = token[idx + 1..token.len() - 1].trim();
-        let args = if args_str.is_empty() {
-            vec![]
-        } else {
-            args_str
-                .split(',')
-                .map(|s| s.trim().parse::<f64>().map_err(|_| format!("bad arg {s}")))
-                .collect::<Result<Vec<_>, _>>()?
-        };
-        Ok((name, args))
-    } else {
-        Ok((token, vec![]))
+fn split_name_and_tag(word: &str) -> Result<(&str, Option<String>), String> {
+    match word.find('[') {
+        Some(idx) if word.ends_with(']') => Ok((
+            &word[..idx],
+            Some(word[idx + 1..word.len() - 1].to_string()),
+        )),
+        Some(_) => Err("bad instruction tag".to_string()),
+        None => Ok((word, None)),
+    }
+}
+
+fn parse_arg(word: &str) -> Result<f64, String> {
+    let val: f64 = word.parse().map_err(|_| "bad arg".to_string())?;
+    if !val.is_finite() {
+        return Err("arg must be finite".to_string());
     }
+    Ok(val)
 }
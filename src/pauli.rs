@@ -0,0 +1,305 @@
+use std::fmt;
+use std::ops::{Bound, Mul, MulAssign, RangeBounds};
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Pauli {
+    I,
+    X,
+    Y,
+    Z,
+}
+
+impl Pauli {
+    /// The non-identity Paulis, in the order noise channels index them.
+    pub const NON_IDENTITY: [Pauli; 3] = [Pauli::X, Pauli::Y, Pauli::Z];
+
+    pub fn from_xz(x: bool, z: bool) -> Self {
+        match (x, z) {
+            (false, false) => Pauli::I,
+            (true, false) => Pauli::X,
+            (true, true) => Pauli::Y,
+            (false, true) => Pauli::Z,
+        }
+    }
+
+    pub fn xz(self) -> (bool, bool) {
+        match self {
+            Pauli::I => (false, false),
+            Pauli::X => (true, false),
+            Pauli::Y => (true, true),
+            Pauli::Z => (false, true),
+        }
+    }
+}
+
+/// The `r`-th of the 15 non-identity two-qubit Paulis, in the order
+/// `DEPOLARIZE2` indexes them (`IX, IY, IZ, XI, ..., ZZ`).
+pub(crate) fn two_qubit_pauli(r: usize) -> (Pauli, Pauli) {
+    const ALL: [Pauli; 4] = [Pauli::I, Pauli::X, Pauli::Y, Pauli::Z];
+    let r = r + 1;
+    (ALL[r / 4], ALL[r % 4])
+}
+
+/// A Pauli product with a phase `i^k`, one X and one Z bit per qubit packed
+/// into `u64` words. Written like Stim: `+XZ_Y`, `-iX`, where `_` or `I` is
+/// the identity.
+///
+/// The gate methods conjugate the string in place, `P -> G P G†`; they are
+/// how [`StabilizerState`](crate::sim::tableau::StabilizerState) updates its
+/// rows.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PauliString {
+    len: usize,
+    phase: u8,
+    xs: Vec<u64>,
+    zs: Vec<u64>,
+}
+
+impl PauliString {
+    /// The identity on `len` qubits.
+    pub fn new(len: usize) -> Self {
+        let words = len.div_ceil(64);
+        Self {
+            len,
+            phase: 0,
+            xs: vec![0; words],
+            zs: vec![0; words],
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The power `k` of the phase `i^k`, in `0..4`.
+    pub fn phase(&self) -> u8 {
+        self.phase
+    }
+
+    /// Sets the phase to `i^phase`.
+    pub fn set_phase(&mut self, phase: u8) {
+        self.phase = phase % 4;
+    }
+
+    /// Whether the phase is `-1` or `-i`.
+    pub fn is_negative(&self) -> bool {
+        self.phase >= 2
+    }
+
+    pub fn set_negative(&mut self, negative: bool) {
+        self.phase = (self.phase & 1) | if negative { 2 } else { 0 };
+    }
+
+    /// Whether the phase is real, i.e. the string is Hermitian.
+    pub fn is_hermitian(&self) -> bool {
+        self.phase.is_multiple_of(2)
+    }
+
+    pub fn get(&self, q: usize) -> Pauli {
+        assert!(q < self.len, "qubit {q} out of range");
+        let bit = |w: &[u64]| w[q / 64] >> (q % 64) & 1 == 1;
+        Pauli::from_xz(bit(&self.xs), bit(&self.zs))
+    }
+
+    pub fn set(&mut self, q: usize, p: Pauli) {
+        assert!(q < self.len, "qubit {q} out of range");
+        let (x, z) = p.xz();
+        let mask = 1u64 << (q % 64);
+        for (words, bit) in [(&mut self.xs, x), (&mut self.zs, z)] {
+            if bit {
+                words[q / 64] |= mask;
+            } else {
+                words[q / 64] &= !mask;
+            }
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Pauli> + '_ {
+        (0..self.len).map(|q| self.get(q))
+    }
+
+    /// Number of non-identity terms.
+    pub fn weight(&self) -> usize {
+        self.xs
+            .iter()
+            .zip(&self.zs)
+            .map(|(x, z)| (x | z).count_ones() as usize)
+            .sum()
+    }
+
+    /// Whether the two strings commute. Qubits past the end of the shorter
+    /// string are treated as identity.
+    pub fn commutes(&self, other: &PauliString) -> bool {
+        let mut parity = 0u32;
+        for w in 0..self.xs.len().min(other.xs.len()) {
+            let anti = (self.xs[w] & other.zs[w]) ^ (self.zs[w] & other.xs[w]);
+            parity ^= anti.count_ones() & 1;
+        }
+        parity == 0
+    }
+
+    /// Copies a range of qubits into a new string with a `+` phase.
+    pub fn slice(&self, range: impl RangeBounds<usize>) -> PauliString {
+        let start = match range.start_bound() {
+            Bound::Included(&s) => s,
+            Bound::Excluded(&s) => s + 1,
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(&e) => e + 1,
+            Bound::Excluded(&e) => e,
+            Bound::Unbounded => self.len,
+        };
+        assert!(start <= end && end <= self.len, "slice out of range");
+        let mut out = PauliString::new(end - start);
+        for q in start..end {
+            out.set(q - start, self.get(q));
+        }
+        out
+    }
+
+    pub fn h(&mut self, q: usize) {
+        let (x, z) = self.get(q).xz();
+        self.flip_sign_if(x && z);
+        self.set(q, Pauli::from_xz(z, x));
+    }
+
+    pub fn s(&mut self, q: usize) {
+        let (x, z) = self.get(q).xz();
+        self.flip_sign_if(x && z);
+        self.set(q, Pauli::from_xz(x, z ^ x));
+    }
+
+    pub fn s_dag(&mut self, q: usize) {
+        let (x, z) = self.get(q).xz();
+        self.flip_sign_if(x && !z);
+        self.set(q, Pauli::from_xz(x, z ^ x));
+    }
+
+    pub fn x_gate(&mut self, q: usize) {
+        let (_, z) = self.get(q).xz();
+        self.flip_sign_if(z);
+    }
+
+    pub fn y_gate(&mut self, q: usize) {
+        let (x, z) = self.get(q).xz();
+        self.flip_sign_if(x ^ z);
+    }
+
+    pub fn z_gate(&mut self, q: usize) {
+        let (x, _) = self.get(q).xz();
+        self.flip_sign_if(x);
+    }
+
+    pub fn cx(&mut self, c: usize, t: usize) {
+        let (xc, zc) = self.get(c).xz();
+        let (xt, zt) = self.get(t).xz();
+        self.flip_sign_if(xc && zt && !(xt ^ zc));
+        self.set(c, Pauli::from_xz(xc, zc ^ zt));
+        self.set(t, Pauli::from_xz(xt ^ xc, zt));
+    }
+
+    pub fn cz(&mut self, a: usize, b: usize) {
+        self.h(b);
+        self.cx(a, b);
+        self.h(b);
+    }
+
+    /// Pads with identities up to `len` qubits; never shrinks.
+    pub(crate) fn grow(&mut self, len: usize) {
+        if len > self.len {
+            let words = len.div_ceil(64);
+            self.len = len;
+            self.xs.resize(words, 0);
+            self.zs.resize(words, 0);
+        }
+    }
+
+    fn flip_sign_if(&mut self, flip: bool) {
+        if flip {
+            self.phase = (self.phase + 2) % 4;
+        }
+    }
+}
+
+impl MulAssign<&PauliString> for PauliString {
+    /// Right-multiplies by `rhs`, growing to the longer length.
+    fn mul_assign(&mut self, rhs: &PauliString) {
+        self.grow(rhs.len);
+        // Count the qubits picking up +i (XY, YZ, ZX) and -i (YX, ZY, XZ)
+        // a word at a time.
+        let mut k = (self.phase + rhs.phase) as u32;
+        for w in 0..rhs.xs.len() {
+            let (x1, z1, x2, z2) = (self.xs[w], self.zs[w], rhs.xs[w], rhs.zs[w]);
+            let (px, py, pz) = (x1 & !z1, x1 & z1, !x1 & z1);
+            let (qx, qy, qz) = (x2 & !z2, x2 & z2, !x2 & z2);
+            let plus = (px & qy) | (py & qz) | (pz & qx);
+            let minus = (py & qx) | (pz & qy) | (px & qz);
+            k += plus.count_ones() + 3 * minus.count_ones();
+            self.xs[w] = x1 ^ x2;
+            self.zs[w] = z1 ^ z2;
+        }
+        self.phase = (k % 4) as u8;
+    }
+}
+
+impl Mul<&PauliString> for &PauliString {
+    type Output = PauliString;
+
+    fn mul(self, rhs: &PauliString) -> PauliString {
+        let mut out = self.clone();
+        out *= rhs;
+        out
+    }
+}
+
+impl FromStr for PauliString {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        let (negative, rest) = match s.as_bytes().first() {
+            Some(b'+') => (false, &s[1..]),
+            Some(b'-') => (true, &s[1..]),
+            _ => (false, s),
+        };
+        let (imaginary, body) = match rest.strip_prefix('i') {
+            Some(body) => (true, body),
+            None => (false, rest),
+        };
+        let mut out = PauliString::new(body.len());
+        out.phase = if negative { 2 } else { 0 } + if imaginary { 1 } else { 0 };
+        for (q, c) in body.chars().enumerate() {
+            let p = match c {
+                '_' | 'I' => Pauli::I,
+                'X' => Pauli::X,
+                'Y' => Pauli::Y,
+                'Z' => Pauli::Z,
+                _ => return Err(format!("bad Pauli character {c:?} in {s:?}")),
+            };
+            out.set(q, p);
+        }
+        Ok(out)
+    }
+}
+
+impl fmt::Display for PauliString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = ["+", "+i", "-", "-i"][self.phase as usize];
+        write!(f, "{sign}")?;
+        for p in self.iter() {
+            let c = match p {
+                Pauli::I => '_',
+                Pauli::X => 'X',
+                Pauli::Y => 'Y',
+                Pauli::Z => 'Z',
+            };
+            write!(f, "{c}")?;
+        }
+        Ok(())
+    }
+}
@@ -12,6 +12,10 @@ impl Recorder {
         self.bits.len()
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.bits.is_empty()
+    }
+
     pub fn rec(&self, offset: i32) -> Option<bool> {
         if offset >= 0 {
             return None;
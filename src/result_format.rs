@@ -0,0 +1,388 @@
+use std::collections::VecDeque;
+use std::io::{self, BufRead, Write};
+use std::str::FromStr;
+
+/// Stim's result formats for measurement, detection event and observable data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResultFormat {
+    /// One line of `0`/`1` characters per shot.
+    Format01,
+    /// Bits packed little-endian into bytes, each shot padded to a whole byte.
+    B8,
+    /// Run lengths of zeros between ones, one byte per run.
+    R8,
+    /// Comma separated indices of the set bits, one line per shot.
+    Hits,
+    /// `shot` followed by `M`/`D`/`L` prefixed indices of the set bits.
+    Dets,
+    /// Shots transposed in groups of 64, each bit stored as a little-endian `u64`.
+    Ptb64,
+}
+
+impl FromStr for ResultFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, String> {
+        match s {
+            "01" => Ok(ResultFormat::Format01),
+            "b8" => Ok(ResultFormat::B8),
+            "r8" => Ok(ResultFormat::R8),
+            "hits" => Ok(ResultFormat::Hits),
+            "dets" => Ok(ResultFormat::Dets),
+            "ptb64" => Ok(ResultFormat::Ptb64),
+            _ => Err(format!("unknown result format {s}")),
+        }
+    }
+}
+
+/// How the bits of one shot split into measurement, detector and observable
+/// sections, in that order. Only the `dets` format distinguishes them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RecordLayout {
+    pub measurements: usize,
+    pub detectors: usize,
+    pub observables: usize,
+}
+
+impl RecordLayout {
+    pub fn measurements(n: usize) -> Self {
+        Self {
+            measurements: n,
+            ..Self::default()
+        }
+    }
+
+    pub fn detectors(detectors: usize, observables: usize) -> Self {
+        Self {
+            measurements: 0,
+            detectors,
+            observables,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.measurements + self.detectors + self.observables
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn section(&self, bit: usize) -> (char, usize) {
+        if bit < self.measurements {
+            ('M', bit)
+        } else if bit < self.measurements + self.detectors {
+            ('D', bit - self.measurements)
+        } else {
+            ('L', bit - self.measurements - self.detectors)
+        }
+    }
+
+    fn offset(&self, prefix: char) -> Option<(usize, usize)> {
+        match prefix {
+            'M' => Some((0, self.measurements)),
+            'D' => Some((self.measurements, self.detectors)),
+            'L' => Some((self.measurements + self.detectors, self.observables)),
+            _ => None,
+        }
+    }
+}
+
+/// Writes shots one at a time. `ptb64` output is buffered in groups of 64
+/// shots, so [`ResultWriter::finish`] must be called to detect a partial group.
+pub struct ResultWriter<W: Write> {
+    out: W,
+    format: ResultFormat,
+    layout: RecordLayout,
+    pending: Vec<Vec<bool>>,
+}
+
+impl<W: Write> ResultWriter<W> {
+    pub fn new(out: W, format: ResultFormat, layout: RecordLayout) -> Self {
+        Self {
+            out,
+            format,
+            layout,
+            pending: Vec::new(),
+        }
+    }
+
+    pub fn write_shot(&mut self, bits: &[bool]) -> io::Result<()> {
+        if bits.len() != self.layout.len() {
+            return Err(invalid(format!(
+                "shot has {} bits, expected {}",
+                bits.len(),
+                self.layout.len()
+            )));
+        }
+        match self.format {
+            ResultFormat::Format01 => {
+                let mut line: Vec<u8> = bits.iter().map(|&b| if b { b'1' } else { b'0' }).collect();
+                line.push(b'\n');
+                self.out.write_all(&line)
+            }
+            ResultFormat::B8 => {
+                let mut bytes = vec![0u8; bits.len().div_ceil(8)];
+                for (i, &b) in bits.iter().enumerate() {
+                    if b {
+                        bytes[i / 8] |= 1 << (i % 8);
+                    }
+                }
+                self.out.write_all(&bytes)
+            }
+            ResultFormat::R8 => {
+                let mut bytes = Vec::new();
+                let mut run = 0usize;
+                for &b in bits.iter().chain(std::iter::once(&true)) {
+                    if !b {
+                        run += 1;
+                        continue;
+                    }
+                    while run >= 255 {
+                        bytes.push(255);
+                        run -= 255;
+                    }
+                    bytes.push(run as u8);
+                    run = 0;
+                }
+                self.out.write_all(&bytes)
+            }
+            ResultFormat::Hits => {
+                let hits: Vec<String> = set_bits(bits).map(|i| i.to_string()).collect();
+                writeln!(self.out, "{}", hits.join(","))
+            }
+            ResultFormat::Dets => {
+                let mut line = String::from("shot");
+                for i in set_bits(bits) {
+                    let (prefix, index) = self.layout.section(i);
+                    line.push_str(&format!(" {prefix}{index}"));
+                }
+                writeln!(self.out, "{line}")
+            }
+            ResultFormat::Ptb64 => {
+                self.pending.push(bits.to_vec());
+                if self.pending.len() == 64 {
+                    self.flush_ptb64()?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    pub fn finish(mut self) -> io::Result<W> {
+        if !self.pending.is_empty() {
+            return Err(invalid(format!(
+                "ptb64 needs a multiple of 64 shots, got {} extra",
+                self.pending.len()
+            )));
+        }
+        self.out.flush()?;
+        Ok(self.out)
+    }
+
+    fn flush_ptb64(&mut self) -> io::Result<()> {
+        let mut bytes = Vec::with_capacity(self.layout.len() * 8);
+        for bit in 0..self.layout.len() {
+            let mut word = 0u64;
+            for (shot, bits) in self.pending.iter().enumerate() {
+                if bits[bit] {
+                    word |= 1 << shot;
+                }
+            }
+            bytes.extend_from_slice(&word.to_le_bytes());
+        }
+        self.pending.clear();
+        self.out.write_all(&bytes)
+    }
+}
+
+/// Reads shots back from any of the supported formats.
+pub struct ResultReader<R: BufRead> {
+    input: R,
+    format: ResultFormat,
+    layout: RecordLayout,
+    pending: VecDeque<Vec<bool>>,
+}
+
+impl<R: BufRead> ResultReader<R> {
+    pub fn new(input: R, format: ResultFormat, layout: RecordLayout) -> Self {
+        Self {
+            input,
+            format,
+            layout,
+            pending: VecDeque::new(),
+        }
+    }
+
+    /// Returns the next shot, or `None` at the end of the input.
+    pub fn read_shot(&mut self) -> Result<Option<Vec<bool>>, String> {
+        let n = self.layout.len();
+        match self.format {
+            ResultFormat::Format01 => {
+                let Some(line) = self.read_text_line()? else {
+                    return Ok(None);
+                };
+                if line.len() != n {
+                    return Err(format!("01 line has {} bits, expected {n}", line.len()));
+                }
+                line.chars()
+                    .map(|c| match c {
+                        '0' => Ok(false),
+                        '1' => Ok(true),
+                        _ => Err(format!("bad 01 character {c:?}")),
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+                    .map(Some)
+            }
+            ResultFormat::B8 => {
+                let Some(bytes) = self.read_bytes(n.div_ceil(8))? else {
+                    return Ok(None);
+                };
+                Ok(Some(
+                    (0..n).map(|i| bytes[i / 8] >> (i % 8) & 1 == 1).collect(),
+                ))
+            }
+            ResultFormat::R8 => {
+                let mut bits = Vec::with_capacity(n);
+                let mut first = true;
+                loop {
+                    let Some(byte) = self.read_bytes(1)? else {
+                        if first {
+                            return Ok(None);
+                        }
+                        return Err("r8 data ended mid-shot".to_string());
+                    };
+                    first = false;
+                    bits.extend(std::iter::repeat_n(false, byte[0] as usize));
+                    if byte[0] != 255 {
+                        bits.push(true);
+                    }
+                    if bits.len() > n + 1 {
+                        return Err(format!("r8 shot longer than {n} bits"));
+                    }
+                    if bits.len() == n + 1 && byte[0] != 255 {
+                        bits.pop();
+                        return Ok(Some(bits));
+                    }
+                }
+            }
+            ResultFormat::Hits => {
+                let Some(line) = self.read_text_line()? else {
+                    return Ok(None);
+                };
+                let mut bits = vec![false; n];
+                for token in line.split(',').filter(|t| !t.trim().is_empty()) {
+                    let i: usize = token
+                        .trim()
+                        .parse()
+                        .map_err(|_| format!("bad hit index {token}"))?;
+                    *bits
+                        .get_mut(i)
+                        .ok_or_else(|| format!("hit index {i} out of range"))? = true;
+                }
+                Ok(Some(bits))
+            }
+            ResultFormat::Dets => {
+                let Some(line) = self.read_text_line()? else {
+                    return Ok(None);
+                };
+                let mut tokens = line.split_whitespace();
+                if tokens.next() != Some("shot") {
+                    return Err(format!("dets line must start with `shot`: {line}"));
+                }
+                let mut bits = vec![false; n];
+                for token in tokens {
+                    let mut chars = token.chars();
+                    let prefix = chars.next().unwrap_or(' ');
+                    let (offset, len) = self
+                        .layout
+                        .offset(prefix)
+                        .ok_or_else(|| format!("bad dets token {token}"))?;
+                    let i: usize = chars
+                        .as_str()
+                        .parse()
+                        .map_err(|_| format!("bad dets token {token}"))?;
+                    if i >= len {
+                        return Err(format!("dets token {token} out of range"));
+                    }
+                    bits[offset + i] = true;
+                }
+                Ok(Some(bits))
+            }
+            ResultFormat::Ptb64 => {
+                if self.pending.is_empty() {
+                    let Some(bytes) = self.read_bytes(n * 8)? else {
+                        return Ok(None);
+                    };
+                    let words: Vec<u64> = bytes
+                        .chunks_exact(8)
+                        .map(|c| u64::from_le_bytes(c.try_into().unwrap()))
+                        .collect();
+                    for shot in 0..64 {
+                        self.pending
+                            .push_back(words.iter().map(|w| w >> shot & 1 == 1).collect());
+                    }
+                }
+                Ok(self.pending.pop_front())
+            }
+        }
+    }
+
+    fn read_text_line(&mut self) -> Result<Option<String>, String> {
+        let mut line = String::new();
+        let read = self.input.read_line(&mut line).map_err(|e| e.to_string())?;
+        if read == 0 {
+            return Ok(None);
+        }
+        Ok(Some(line.trim_end_matches(['\n', '\r']).to_string()))
+    }
+
+    fn read_bytes(&mut self, len: usize) -> Result<Option<Vec<u8>>, String> {
+        if len == 0 || self.input.fill_buf().map_err(|e| e.to_string())?.is_empty() {
+            return Ok(None);
+        }
+        let mut buf = vec![0u8; len];
+        self.input
+            .read_exact(&mut buf)
+            .map_err(|_| "binary result data ended mid-shot".to_string())?;
+        Ok(Some(buf))
+    }
+}
+
+impl<R: BufRead> Iterator for ResultReader<R> {
+    type Item = Result<Vec<bool>, String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_shot().transpose()
+    }
+}
+
+/// Formats shots into a byte buffer.
+pub fn write_shots(
+    shots: &[Vec<bool>],
+    format: ResultFormat,
+    layout: RecordLayout,
+) -> io::Result<Vec<u8>> {
+    let mut w = ResultWriter::new(Vec::new(), format, layout);
+    for shot in shots {
+        w.write_shot(shot)?;
+    }
+    w.finish()
+}
+
+/// Parses every shot in `data`.
+pub fn read_shots(
+    data: &[u8],
+    format: ResultFormat,
+    layout: RecordLayout,
+) -> Result<Vec<Vec<bool>>, String> {
+    ResultReader::new(data, format, layout).collect()
+}
+
+fn set_bits(bits: &[bool]) -> impl Iterator<Item = usize> + '_ {
+    bits.iter().enumerate().filter(|(_, b)| **b).map(|(i, _)| i)
+}
+
+fn invalid(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, msg)
+}
@@ -0,0 +1,244 @@
+use std::ops::Range;
+
+use crate::circuit::Circuit;
+use crate::compiled::CompiledCircuit;
+use crate::ir::StimInstr;
+use crate::seed::ShotRng;
+use crate::sim::frame::{FrameRecord, FrameSimulator};
+use crate::sim::reference::{compile_noiseless, reference_sample};
+use crate::stats::stats;
+
+/// A shots-by-bits table of sampled results, each row packed into `u64` words.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BitTable {
+    rows: usize,
+    cols: usize,
+    words_per_row: usize,
+    words: Vec<u64>,
+}
+
+impl BitTable {
+    pub fn new(rows: usize, cols: usize) -> Self {
+        let words_per_row = cols.div_ceil(64);
+        Self {
+            rows,
+            cols,
+            words_per_row,
+            words: vec![0; rows * words_per_row],
+        }
+    }
+
+    pub fn num_rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn num_cols(&self) -> usize {
+        self.cols
+    }
+
+    pub fn get(&self, row: usize, col: usize) -> bool {
+        assert!(
+            row < self.rows && col < self.cols,
+            "bit ({row}, {col}) out of range"
+        );
+        self.words[row * self.words_per_row + col / 64] >> (col % 64) & 1 == 1
+    }
+
+    pub fn set(&mut self, row: usize, col: usize, value: bool) {
+        assert!(
+            row < self.rows && col < self.cols,
+            "bit ({row}, {col}) out of range"
+        );
+        let word = &mut self.words[row * self.words_per_row + col / 64];
+        let mask = 1u64 << (col % 64);
+        if value {
+            *word |= mask;
+        } else {
+            *word &= !mask;
+        }
+    }
+
+    pub fn row(&self, row: usize) -> Vec<bool> {
+        (0..self.cols).map(|c| self.get(row, c)).collect()
+    }
+}
+
+/// Shots simulated together as the lanes of one frame simulator, and the
+/// unit of work handed to a thread.
+pub const CHUNK_SHOTS: usize = 256;
+
+/// Samples measurement records from a circuit that is compiled once and
+/// reused for every shot.
+///
+/// Shots are simulated in aligned blocks of [`CHUNK_SHOTS`], one lane of a
+/// [`FrameSimulator`] per shot, and each measurement is a lane's flip of a
+/// single noiseless reference sample. Block `b` holds shots
+/// `b * CHUNK_SHOTS..(b + 1) * CHUNK_SHOTS` and draws from
+/// `ShotRng::new(seed, b)`; a block is always simulated whole, so a shot
+/// depends only on the seed and its index, not on how shots are batched or
+/// split across threads.
+pub struct Sampler {
+    program: CompiledCircuit,
+    reference: Vec<bool>,
+    num_observables: usize,
+    seed: u64,
+    threads: usize,
+}
+
+impl Sampler {
+    pub fn new(instrs: Vec<StimInstr>, seed: u64) -> Result<Self, String> {
+        let program = CompiledCircuit::compile(&instrs)?;
+        let reference = reference_sample(&compile_noiseless(&instrs)?, &[])?;
+        Ok(Self {
+            program,
+            reference,
+            num_observables: stats(&instrs).num_observables as usize,
+            seed,
+            threads: 1,
+        })
+    }
+
+    pub fn from_circuit(circuit: &Circuit, seed: u64) -> Result<Self, String> {
+        Self::new(circuit.instrs.clone(), seed)
+    }
+
+    /// Splits shots across up to `threads` worker threads (at least one).
+    pub fn set_threads(&mut self, threads: usize) {
+        self.threads = threads.max(1);
+    }
+
+    pub fn num_measurements(&self) -> usize {
+        self.reference.len()
+    }
+
+    pub fn num_detectors(&self) -> usize {
+        self.program.num_detectors() as usize
+    }
+
+    pub fn num_observables(&self) -> usize {
+        self.num_observables
+    }
+
+    /// Samples `shots` measurement records, one row per shot.
+    pub fn sample(&self, shots: usize) -> Result<BitTable, String> {
+        self.sample_range(0, shots)
+    }
+
+    /// Samples shots `first_shot..first_shot + shots`; row `i` of the table
+    /// holds shot `first_shot + i`.
+    pub fn sample_range(&self, first_shot: u64, shots: usize) -> Result<BitTable, String> {
+        let blocks = self.simulate(first_shot, shots, true)?;
+        Ok(blocks.gather(self.num_measurements(), |record, c, lane| {
+            self.reference[c] ^ lane_bit(&record.measurements[c], lane)
+        }))
+    }
+
+    /// Samples `shots` detection events and observable flips as
+    /// `(detectors, observables)` tables, one row per shot.
+    pub fn detect(&self, shots: usize) -> Result<(BitTable, BitTable), String> {
+        self.detect_range(0, shots)
+    }
+
+    /// Samples shots `first_shot..first_shot + shots` like
+    /// [`sample_range`](Self::sample_range), but keeps only which detectors
+    /// and observables each shot flips relative to the noiseless reference.
+    pub fn detect_range(
+        &self,
+        first_shot: u64,
+        shots: usize,
+    ) -> Result<(BitTable, BitTable), String> {
+        let blocks = self.simulate(first_shot, shots, false)?;
+        let detectors = blocks.gather(self.num_detectors(), |record, d, lane| {
+            lane_bit(&record.detectors[d], lane)
+        });
+        let observables = blocks.gather(self.num_observables, |record, o, lane| {
+            record
+                .observables
+                .get(o)
+                .is_some_and(|words| lane_bit(words, lane))
+        });
+        Ok((detectors, observables))
+    }
+
+    /// Simulates every block overlapping shots `first_shot..first_shot +
+    /// shots`, handing contiguous runs of blocks to the worker threads.
+    fn simulate(
+        &self,
+        first_shot: u64,
+        shots: usize,
+        keep_measurements: bool,
+    ) -> Result<Blocks, String> {
+        let block = CHUNK_SHOTS as u64;
+        let first_block = first_shot / block;
+        let num_blocks = ((first_shot + shots as u64).div_ceil(block) - first_block) as usize;
+        let threads = self.threads.min(num_blocks).max(1);
+        let per_thread = num_blocks.div_ceil(threads).max(1);
+        let results: Vec<Result<Vec<FrameRecord>, String>> = std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..num_blocks)
+                .step_by(per_thread)
+                .map(|start| {
+                    let end = (start + per_thread).min(num_blocks);
+                    let blocks = first_block + start as u64..first_block + end as u64;
+                    scope.spawn(move || self.simulate_blocks(blocks, keep_measurements))
+                })
+                .collect();
+            handles
+                .into_iter()
+                .map(|h| h.join().expect("sampler thread panicked"))
+                .collect()
+        });
+        let mut records = Vec::with_capacity(num_blocks);
+        for result in results {
+            records.extend(result?);
+        }
+        Ok(Blocks {
+            first_shot,
+            shots,
+            first_block,
+            records,
+        })
+    }
+
+    fn simulate_blocks(
+        &self,
+        blocks: Range<u64>,
+        keep_measurements: bool,
+    ) -> Result<Vec<FrameRecord>, String> {
+        let mut sim = FrameSimulator::new(self.program.num_qubits(), CHUNK_SHOTS);
+        sim.keep_measurements(keep_measurements);
+        blocks
+            .map(|b| sim.run(&self.program, &mut ShotRng::new(self.seed, b)))
+            .collect()
+    }
+}
+
+/// The frame records of consecutive blocks covering a range of shots.
+struct Blocks {
+    first_shot: u64,
+    shots: usize,
+    first_block: u64,
+    records: Vec<FrameRecord>,
+}
+
+impl Blocks {
+    /// One row per shot of the range, bit `c` of a row being
+    /// `bit(record, c, lane)` for the shot's block record and lane.
+    fn gather(&self, cols: usize, bit: impl Fn(&FrameRecord, usize, usize) -> bool) -> BitTable {
+        let mut table = BitTable::new(self.shots, cols);
+        for row in 0..self.shots {
+            let shot = self.first_shot + row as u64;
+            let record = &self.records[(shot / CHUNK_SHOTS as u64 - self.first_block) as usize];
+            let lane = (shot % CHUNK_SHOTS as u64) as usize;
+            for c in 0..cols {
+                if bit(record, c, lane) {
+                    table.set(row, c, true);
+                }
+            }
+        }
+        table
+    }
+}
+
+fn lane_bit(words: &[u64], lane: usize) -> bool {
+    words[lane / 64] >> (lane % 64) & 1 == 1
+}
@@ -0,0 +1,125 @@
+use rand::{Error, RngCore};
+
+/// A random stream determined by a `(seed, stream)` pair alone.
+///
+/// [`Sampler`](crate::sampler::Sampler) gives every aligned block of
+/// [`CHUNK_SHOTS`](crate::sampler::CHUNK_SHOTS) shots its own stream and
+/// always simulates a block whole, so a shot never depends on batch size,
+/// thread count or which other shots were sampled. A block's words are read
+/// in program order: one random gauge word per 64 lanes for every qubit at
+/// the start and for every measured or reset target, and for every noise
+/// target (or `DEPOLARIZE2` pair) the words of [`hits`] over the block's
+/// lanes followed, for `DEPOLARIZE1`/`DEPOLARIZE2`, by one [`uniform`] per
+/// firing lane to pick the Pauli. Noise is thus skip-sampled across shots.
+///
+/// A single [`Executor`](crate::executor::Executor) run reads a stream as
+/// one shot instead: one [`coin`] per random measurement outcome, and the
+/// words of [`hits`] over each noise instruction's targets followed by the
+/// Pauli picks.
+///
+/// The generator (xoshiro256** seeded through splitmix64) is part of the
+/// contract; changing it changes every seeded output.
+#[derive(Debug, Clone)]
+pub struct ShotRng {
+    s: [u64; 4],
+}
+
+impl ShotRng {
+    pub fn new(seed: u64, stream: u64) -> Self {
+        // Hashing the seed before the stream is folded in means no XOR of a
+        // seed with a stream's hash can land two pairs on the same state.
+        let mut x = splitmix64(splitmix64(seed) ^ stream);
+        let mut s = [0u64; 4];
+        for word in s.iter_mut() {
+            x = x.wrapping_add(0x9E37_79B9_7F4A_7C15);
+            *word = splitmix64(x);
+        }
+        Self { s }
+    }
+}
+
+impl RngCore for ShotRng {
+    fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let s = &mut self.s;
+        let result = s[1].wrapping_mul(5).rotate_left(7).wrapping_mul(9);
+        let t = s[1] << 17;
+        s[2] ^= s[0];
+        s[3] ^= s[1];
+        s[1] ^= s[2];
+        s[0] ^= s[3];
+        s[2] ^= t;
+        s[3] = s[3].rotate_left(45);
+        result
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(8) {
+            let bytes = self.next_u64().to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+/// A uniform value in `[0, 1)` from one 64-bit word.
+pub fn uniform(rng: &mut impl RngCore) -> f64 {
+    (rng.next_u64() >> 11) as f64 * (1.0 / (1u64 << 53) as f64)
+}
+
+/// A fair coin from the top bit of one 64-bit word.
+pub fn coin(rng: &mut impl RngCore) -> bool {
+    rng.next_u64() >> 63 == 1
+}
+
+/// Picks one of `n` outcomes uniformly from one 64-bit word.
+pub(crate) fn pick(rng: &mut impl RngCore, n: usize) -> usize {
+    ((uniform(rng) * n as f64) as usize).min(n - 1)
+}
+
+/// Indices of the trials among `n` independent ones that fire with
+/// probability `p`. Rather than one draw per trial, each [`uniform`] draw
+/// gives the geometrically distributed gap to the next firing trial, so
+/// rare events cost one draw plus one per hit. `p <= 0` and `p >= 1` draw
+/// nothing.
+///
+/// The trials are the targets of one instruction within a shot, or the
+/// shots of a block sharing one stream; see [`ShotRng`].
+pub fn hits(rng: &mut impl RngCore, p: f64, n: usize) -> Vec<usize> {
+    if p <= 0.0 || n == 0 {
+        return Vec::new();
+    }
+    if p >= 1.0 {
+        return (0..n).collect();
+    }
+    let log_miss = (-p).ln_1p();
+    let mut out = Vec::new();
+    let mut next = 0usize;
+    loop {
+        // 1 - u lies in (0, 1], so the logarithm is finite.
+        let gap = ((1.0 - uniform(rng)).ln() / log_miss).floor();
+        if gap >= (n - next) as f64 {
+            return out;
+        }
+        next += gap as usize;
+        out.push(next);
+        next += 1;
+        if next == n {
+            return out;
+        }
+    }
+}
+
+fn splitmix64(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
@@ -0,0 +1,137 @@
+use rand::Rng;
+
+use crate::compiled::Basis;
+use crate::pauli::Pauli;
+use crate::seed::coin;
+use crate::sim::tableau::StabilizerState;
+
+/// A quantum state that [`Executor`](crate::executor::Executor) can drive.
+/// The executor owns the instruction semantics (loops, records, noise
+/// sampling, sweep controls); a backend only applies the primitive
+/// operations, so any simulator, or a wrapper that logs or checks calls,
+/// runs circuits the same way.
+pub trait SimulatorBackend {
+    /// Largest register the backend can hold.
+    const MAX_QUBITS: usize = usize::MAX;
+
+    /// `n` qubits, all in `|0>`.
+    fn with_qubits(n: usize) -> Self;
+
+    fn num_qubits(&self) -> usize;
+
+    /// Grows the register to at least `n` qubits, the new ones in `|0>`.
+    /// Backends that cannot grow refuse anything past their current size.
+    fn ensure_qubits(&mut self, n: usize) -> Result<(), String> {
+        if n > self.num_qubits() {
+            return Err(format!(
+                "backend has {} qubits and cannot grow to {n}",
+                self.num_qubits()
+            ));
+        }
+        Ok(())
+    }
+
+    fn h(&mut self, q: usize);
+
+    fn s(&mut self, q: usize);
+
+    /// `S†`, by default as three `S` gates.
+    fn s_dag(&mut self, q: usize) {
+        self.s(q);
+        self.s(q);
+        self.s(q);
+    }
+
+    fn cx(&mut self, c: usize, t: usize);
+
+    fn cz(&mut self, c: usize, t: usize);
+
+    fn apply_pauli(&mut self, q: usize, p: Pauli);
+
+    /// Measures `q` in `basis`, collapsing onto the observed eigenstate;
+    /// `true` is the `-1` outcome.
+    fn measure(&mut self, basis: Basis, q: usize, rng: &mut impl Rng) -> bool;
+
+    /// Resets `q` to the `+1` eigenstate of `basis`.
+    fn reset(&mut self, basis: Basis, q: usize, rng: &mut impl Rng) {
+        // Measuring projects onto the basis; a 1 is then flipped back.
+        if self.measure(basis, q, rng) {
+            let flip = match basis {
+                Basis::X => Pauli::Z,
+                _ => Pauli::X,
+            };
+            self.apply_pauli(q, flip);
+        }
+    }
+}
+
+impl SimulatorBackend for StabilizerState {
+    fn with_qubits(n: usize) -> Self {
+        Self::new(n)
+    }
+
+    fn num_qubits(&self) -> usize {
+        self.num_qubits()
+    }
+
+    fn ensure_qubits(&mut self, n: usize) -> Result<(), String> {
+        self.ensure_qubits(n);
+        Ok(())
+    }
+
+    fn h(&mut self, q: usize) {
+        self.h(q);
+    }
+
+    fn s(&mut self, q: usize) {
+        self.s(q);
+    }
+
+    fn s_dag(&mut self, q: usize) {
+        self.s_dag(q);
+    }
+
+    fn cx(&mut self, c: usize, t: usize) {
+        self.cx(c, t);
+    }
+
+    fn cz(&mut self, c: usize, t: usize) {
+        self.cz(c, t);
+    }
+
+    fn apply_pauli(&mut self, q: usize, p: Pauli) {
+        self.apply_pauli(q, p);
+    }
+
+    fn measure(&mut self, basis: Basis, q: usize, rng: &mut impl Rng) -> bool {
+        measure_tableau(self, basis, q, || coin(rng))
+    }
+}
+
+/// Measures `q` in `basis`, calling `random_outcome` only when the outcome
+/// is not already determined.
+pub(crate) fn measure_tableau(
+    state: &mut StabilizerState,
+    basis: Basis,
+    q: usize,
+    random_outcome: impl FnOnce() -> bool,
+) -> bool {
+    let bit = match basis {
+        Basis::Z => state.collapse_z(q, random_outcome).0,
+        Basis::X => {
+            state.h(q);
+            let (bit, _) = state.collapse_z(q, random_outcome);
+            state.h(q);
+            bit
+        }
+        Basis::Y => {
+            state.s_dag(q);
+            state.h(q);
+            let (bit, _) = state.collapse_z(q, random_outcome);
+            state.h(q);
+            state.s(q);
+            bit
+        }
+    };
+    bit == 1
+}
@@ -0,0 +1,314 @@
+use std::collections::VecDeque;
+
+use rand::RngCore;
+
+use crate::compiled::{Basis, CompiledCircuit, LoopStack, Op};
+use crate::executor::Control;
+use crate::pauli::{Pauli, two_qubit_pauli};
+use crate::seed::{hits, pick};
+
+/// Pauli frame simulator tracking many independent frames ("lanes") at once,
+/// one bit per lane packed into `u64` words.
+///
+/// A frame records how a noisy execution deviates from a reference execution
+/// by a Pauli product. Clifford gates conjugate the frame and a measurement
+/// is flipped when its frame anticommutes with the measured observable.
+#[derive(Debug, Clone)]
+pub struct FrameSimulator {
+    n: usize,
+    lanes: usize,
+    x: Vec<Vec<u64>>,
+    z: Vec<Vec<u64>>,
+    keep_measurements: bool,
+}
+
+impl FrameSimulator {
+    pub fn new(n: usize, lanes: usize) -> Self {
+        let words = lanes.div_ceil(64);
+        Self {
+            n,
+            lanes,
+            x: vec![vec![0; words]; n],
+            z: vec![vec![0; words]; n],
+            keep_measurements: false,
+        }
+    }
+
+    pub fn num_qubits(&self) -> usize {
+        self.n
+    }
+
+    pub fn num_lanes(&self) -> usize {
+        self.lanes
+    }
+
+    /// Makes [`run`](Self::run) return the flips of every measurement, not
+    /// just detectors and observables.
+    pub fn keep_measurements(&mut self, keep: bool) {
+        self.keep_measurements = keep;
+    }
+
+    pub fn h(&mut self, q: usize) {
+        std::mem::swap(&mut self.x[q], &mut self.z[q]);
+    }
+
+    pub fn s(&mut self, q: usize) {
+        for (z, x) in self.z[q].iter_mut().zip(&self.x[q]) {
+            *z ^= x;
+        }
+    }
+
+    pub fn cx(&mut self, c: usize, t: usize) {
+        for w in 0..self.x[c].len() {
+            self.x[t][w] ^= self.x[c][w];
+            self.z[c][w] ^= self.z[t][w];
+        }
+    }
+
+    pub fn cz(&mut self, a: usize, b: usize) {
+        for w in 0..self.x[a].len() {
+            self.z[a][w] ^= self.x[b][w];
+            self.z[b][w] ^= self.x[a][w];
+        }
+    }
+
+    /// Lanes in which a Z-basis measurement of `q` is flipped.
+    pub fn measure_z(&self, q: usize) -> Vec<u64> {
+        self.x[q].clone()
+    }
+
+    /// Lanes in which an X-basis measurement of `q` is flipped.
+    pub fn measure_x(&self, q: usize) -> Vec<u64> {
+        self.z[q].clone()
+    }
+
+    /// Lanes in which a Y-basis measurement of `q` is flipped.
+    pub fn measure_y(&self, q: usize) -> Vec<u64> {
+        self.x[q]
+            .iter()
+            .zip(&self.z[q])
+            .map(|(x, z)| x ^ z)
+            .collect()
+    }
+
+    pub fn reset(&mut self, q: usize) {
+        self.x[q].fill(0);
+        self.z[q].fill(0);
+    }
+
+    /// Toggles an X error on `q` in a single lane.
+    pub fn flip_x(&mut self, q: usize, lane: usize) {
+        self.x[q][lane / 64] ^= 1 << (lane % 64);
+    }
+
+    /// Toggles a Z error on `q` in a single lane.
+    pub fn flip_z(&mut self, q: usize, lane: usize) {
+        self.z[q][lane / 64] ^= 1 << (lane % 64);
+    }
+}
+
+/// Per-lane flips produced by [`FrameSimulator::run`], relative to a
+/// noiseless reference execution.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FrameRecord {
+    /// Flips of each measurement in record order, if
+    /// [`keep_measurements`](FrameSimulator::keep_measurements) is set.
+    pub measurements: Vec<Vec<u64>>,
+    pub detectors: Vec<Vec<u64>>,
+    /// Flips of each observable, indexed by observable.
+    pub observables: Vec<Vec<u64>>,
+}
+
+impl FrameSimulator {
+    /// Runs a compiled circuit in every lane at once, sampling noise
+    /// independently per lane. Random measurement outcomes are modelled by
+    /// randomizing the gauge after each measurement, so measurement flips are
+    /// relative to any reference sample and detector flips are detection
+    /// events. Sweep controls belong to the reference and are skipped.
+    ///
+    /// Unless [`keep_measurements`](Self::keep_measurements) is set,
+    /// measurement flips are kept only as far back as the circuit's `rec`
+    /// targets reach.
+    pub fn run(
+        &mut self,
+        program: &CompiledCircuit,
+        rng: &mut impl RngCore,
+    ) -> Result<FrameRecord, String> {
+        if program.num_qubits() > self.n {
+            return Err(format!(
+                "circuit uses {} qubits but the simulator has {}",
+                program.num_qubits(),
+                self.n
+            ));
+        }
+        let words = self.lanes.div_ceil(64);
+        for q in 0..self.n {
+            self.x[q].fill(0);
+            self.z[q] = random_words(rng, self.lanes);
+        }
+        let mut record = FrameRecord::default();
+        let lookback = program.max_lookback();
+        let mut recent: VecDeque<Vec<u64>> = VecDeque::with_capacity(lookback);
+        let mut loops = LoopStack::default();
+        let ops = program.ops();
+        let mut pc = 0;
+        while pc < ops.len() {
+            if let Some(next) = loops.jump(ops, pc) {
+                pc = next;
+                continue;
+            }
+            match &ops[pc] {
+                Op::H(qs) => qs.iter().for_each(|&q| self.h(q)),
+                Op::S(qs) | Op::SDag(qs) => qs.iter().for_each(|&q| self.s(q)),
+                Op::X(_) | Op::Y(_) | Op::Z(_) => {}
+                Op::Cx(pairs) => {
+                    for &(c, t) in pairs {
+                        if let Control::Qubit(c) = c {
+                            self.cx(c, t);
+                        }
+                    }
+                }
+                Op::Cz(pairs) => {
+                    for &(c, t) in pairs {
+                        if let Control::Qubit(c) = c {
+                            self.cz(c, t);
+                        }
+                    }
+                }
+                Op::Measure { basis, targets } => {
+                    for &(q, _) in targets {
+                        let gauge = random_words(rng, self.lanes);
+                        let flips = match basis {
+                            Basis::Z => {
+                                let flips = self.measure_z(q);
+                                xor_into(&mut self.z[q], &gauge);
+                                flips
+                            }
+                            Basis::X => {
+                                let flips = self.measure_x(q);
+                                xor_into(&mut self.x[q], &gauge);
+                                flips
+                            }
+                            Basis::Y => {
+                                let flips = self.measure_y(q);
+                                xor_into(&mut self.x[q], &gauge);
+                                xor_into(&mut self.z[q], &gauge);
+                                flips
+                            }
+                        };
+                        if self.keep_measurements {
+                            record.measurements.push(flips.clone());
+                        }
+                        if lookback > 0 {
+                            if recent.len() == lookback {
+                                recent.pop_front();
+                            }
+                            recent.push_back(flips);
+                        }
+                    }
+                }
+                Op::Reset { basis, targets } => {
+                    // The reset state absorbs errors in its own basis, so
+                    // that frame component becomes a fresh random gauge.
+                    for &q in targets {
+                        let gauge = random_words(rng, self.lanes);
+                        self.reset(q);
+                        match basis {
+                            Basis::Z => xor_into(&mut self.z[q], &gauge),
+                            Basis::X => xor_into(&mut self.x[q], &gauge),
+                            Basis::Y => {
+                                xor_into(&mut self.x[q], &gauge);
+                                xor_into(&mut self.z[q], &gauge);
+                            }
+                        }
+                    }
+                }
+                Op::XError { p, targets } => {
+                    for &q in targets {
+                        for lane in hits(rng, *p, self.lanes) {
+                            self.flip_x(q, lane);
+                        }
+                    }
+                }
+                Op::ZError { p, targets } => {
+                    for &q in targets {
+                        for lane in hits(rng, *p, self.lanes) {
+                            self.flip_z(q, lane);
+                        }
+                    }
+                }
+                Op::Depolarize1 { p, targets } => {
+                    for &q in targets {
+                        for lane in hits(rng, *p, self.lanes) {
+                            self.flip_pauli(q, lane, Pauli::NON_IDENTITY[pick(rng, 3)]);
+                        }
+                    }
+                }
+                Op::Depolarize2 { p, pairs } => {
+                    for &(a, b) in pairs {
+                        for lane in hits(rng, *p, self.lanes) {
+                            let (pa, pb) = two_qubit_pauli(pick(rng, 15));
+                            self.flip_pauli(a, lane, pa);
+                            self.flip_pauli(b, lane, pb);
+                        }
+                    }
+                }
+                Op::Detector { recs, .. } => {
+                    let flips = xor_recorded(&recent, recs, words);
+                    record.detectors.push(flips);
+                }
+                Op::ObservableInclude { index, recs } => {
+                    let flips = xor_recorded(&recent, recs, words);
+                    let index = *index as usize;
+                    if index >= record.observables.len() {
+                        record.observables.resize(index + 1, vec![0; words]);
+                    }
+                    xor_into(&mut record.observables[index], &flips);
+                }
+                Op::QubitCoords { .. } | Op::ShiftCoords(_) | Op::Tick => {}
+                Op::Repeat { .. } | Op::EndRepeat { .. } => unreachable!("handled by LoopStack"),
+            }
+            pc += 1;
+        }
+        Ok(record)
+    }
+
+    /// Toggles Pauli `p` on `q` in one lane.
+    fn flip_pauli(&mut self, q: usize, lane: usize, p: Pauli) {
+        let (x, z) = p.xz();
+        if x {
+            self.flip_x(q, lane);
+        }
+        if z {
+            self.flip_z(q, lane);
+        }
+    }
+}
+
+/// Random bits for each of `lanes` lanes, leaving the padding bits zero.
+fn random_words(rng: &mut impl RngCore, lanes: usize) -> Vec<u64> {
+    let mut out: Vec<u64> = (0..lanes.div_ceil(64)).map(|_| rng.next_u64()).collect();
+    if !lanes.is_multiple_of(64)
+        && let Some(last) = out.last_mut()
+    {
+        *last &= (1 << (lanes % 64)) - 1;
+    }
+    out
+}
+
+fn xor_into(acc: &mut [u64], words: &[u64]) {
+    for (a, w) in acc.iter_mut().zip(words) {
+        *a ^= w;
+    }
+}
+
+fn xor_recorded(measurements: &VecDeque<Vec<u64>>, recs: &[i32], words: usize) -> Vec<u64> {
+    let mut out = vec![0; words];
+    for &o in recs {
+        xor_into(
+            &mut out,
+            &measurements[measurements.len() - o.unsigned_abs() as usize],
+        );
+    }
+    out
+}
@@ -1 +1,4 @@
 pub mod tableau;
+pub mod frame;
+pub mod backend;
+pub(crate) mod reference;
@@ -0,0 +1,75 @@
+use rand::Rng;
+
+use crate::circuit::Circuit;
+use crate::compiled::{Basis, CompiledCircuit};
+use crate::executor::Executor;
+use crate::ir::StimInstr;
+use crate::pauli::Pauli;
+use crate::seed::ShotRng;
+use crate::sim::backend::{SimulatorBackend, measure_tableau};
+use crate::sim::tableau::StabilizerState;
+
+/// Compiles `instrs` with every noise channel removed.
+pub(crate) fn compile_noiseless(instrs: &[StimInstr]) -> Result<CompiledCircuit, String> {
+    let noiseless = Circuit::from_instrs(instrs.to_vec()).without_noise().instrs;
+    CompiledCircuit::compile(&noiseless)
+}
+
+/// The measurement record of one noiseless execution of `noiseless` in
+/// which every random measurement takes the value 0.
+pub(crate) fn reference_sample(
+    noiseless: &CompiledCircuit,
+    sweep_bits: &[bool],
+) -> Result<Vec<bool>, String> {
+    let mut ex = Executor::<ZeroOutcomes>::with_program(noiseless.clone());
+    ex.set_sweep_bits(sweep_bits.to_vec());
+    // Without noise and with measurements never drawing, nothing reads this.
+    let mut rng = ShotRng::new(0, 0);
+    Ok(ex.run(&mut rng)?.measurements)
+}
+
+/// A tableau whose random measurements all come out 0.
+struct ZeroOutcomes(StabilizerState);
+
+impl SimulatorBackend for ZeroOutcomes {
+    fn with_qubits(n: usize) -> Self {
+        Self(StabilizerState::new(n))
+    }
+
+    fn num_qubits(&self) -> usize {
+        self.0.num_qubits()
+    }
+
+    fn ensure_qubits(&mut self, n: usize) -> Result<(), String> {
+        self.0.ensure_qubits(n);
+        Ok(())
+    }
+
+    fn h(&mut self, q: usize) {
+        self.0.h(q);
+    }
+
+    fn s(&mut self, q: usize) {
+        self.0.s(q);
+    }
+
+    fn s_dag(&mut self, q: usize) {
+        self.0.s_dag(q);
+    }
+
+    fn cx(&mut self, c: usize, t: usize) {
+        self.0.cx(c, t);
+    }
+
+    fn cz(&mut self, c: usize, t: usize) {
+        self.0.cz(c, t);
+    }
+
+    fn apply_pauli(&mut self, q: usize, p: Pauli) {
+        self.0.apply_pauli(q, p);
+    }
+
+    fn measure(&mut self, basis: Basis, q: usize, _rng: &mut impl Rng) -> bool {
+        measure_tableau(&mut self.0, basis, q, || false)
+    }
+}
@@ -1,192 +1,596 @@
+use num_complex::Complex64;
 use rand::Rng;
 
+use crate::pauli::{Pauli, PauliString};
+use crate::seed::coin;
+
+/// Largest state handled by the state-vector conversions.
+const MAX_STATE_VECTOR_QUBITS: usize = 24;
+/// Amplitude tolerance when recognizing stabilizer states.
+const TOLERANCE: f64 = 1e-6;
+
 #[derive(Debug, Clone)]
 pub struct StabilizerState {
     n: usize,
-    x: Vec<Vec<bool>>, // 2n rows
-    z: Vec<Vec<bool>>, // 2n rows
-    phase: Vec<u8>,    // mod 4, represents i^phase
+    rows: Vec<PauliString>, // n destabilizers, then n stabilizers
 }
 
 impl StabilizerState {
     pub fn new(n: usize) -> Self {
-        let mut x = vec![vec![false; n]; 2 * n];
-        let mut z = vec![vec![false; n]; 2 * n];
-        let phase = vec![0u8; 2 * n];
         // Initialize to |0..0>, destabilizers are X_i, stabilizers are Z_i
-        for i in 0..n {
-            x[i][i] = true; // destabilizer X
-            z[i + n][i] = true; // stabilizer Z
+        let rows = [Pauli::X, Pauli::Z]
+            .into_iter()
+            .flat_map(|p| (0..n).map(move |i| single(n, i, p)))
+            .collect();
+        Self { n, rows }
+    }
+
+    pub fn num_qubits(&self) -> usize {
+        self.n
+    }
+
+    /// Grows the register to at least `n` qubits, the new ones in `|0>`,
+    /// leaving the state of the existing qubits untouched.
+    pub fn ensure_qubits(&mut self, n: usize) {
+        let old = self.n;
+        if n <= old {
+            return;
+        }
+        // Keep destabilizers before stabilizers: old rows, then X_q / Z_q
+        // for each new qubit q.
+        let mut old_rows = std::mem::take(&mut self.rows).into_iter();
+        let mut rows = Vec::with_capacity(2 * n);
+        for new_pauli in [Pauli::X, Pauli::Z] {
+            for mut row in old_rows.by_ref().take(old) {
+                row.grow(n);
+                rows.push(row);
+            }
+            rows.extend((old..n).map(|q| single(n, q, new_pauli)));
         }
-        Self { n, x, z, phase }
+        self.n = n;
+        self.rows = rows;
     }
 
     pub fn h(&mut self, q: usize) {
-        for i in 0..2 * self.n {
-            if self.x[i][q] && self.z[i][q] {
-                self.phase[i] = (self.phase[i] + 2) % 4;
-            }
-            let tmp = self.x[i][q];
-            self.x[i][q] = self.z[i][q];
-            self.z[i][q] = tmp;
+        for row in &mut self.rows {
+            row.h(q);
         }
     }
 
     pub fn s(&mut self, q: usize) {
-        for i in 0..2 * self.n {
-            if self.x[i][q] && self.z[i][q] {
-                self.phase[i] = (self.phase[i] + 2) % 4;
-            }
-            self.z[i][q] ^= self.x[i][q];
+        for row in &mut self.rows {
+            row.s(q);
         }
     }
 
     pub fn s_dag(&mut self, q: usize) {
-        // S^\u2020 is S applied three times
-        self.s(q);
-        self.s(q);
-        self.s(q);
+        for row in &mut self.rows {
+            row.s_dag(q);
+        }
     }
 
     pub fn x_gate(&mut self, q: usize) {
-        for i in 0..2 * self.n {
-            if self.z[i][q] {
-                self.phase[i] = (self.phase[i] + 2) % 4;
-            }
+        for row in &mut self.rows {
+            row.x_gate(q);
         }
     }
 
     pub fn z_gate(&mut self, q: usize) {
-        for i in 0..2 * self.n {
-            if self.x[i][q] {
-                self.phase[i] = (self.phase[i] + 2) % 4;
-            }
+        for row in &mut self.rows {
+            row.z_gate(q);
         }
     }
 
     pub fn y_gate(&mut self, q: usize) {
-        self.x_gate(q);
-        self.z_gate(q);
+        for row in &mut self.rows {
+            row.y_gate(q);
+        }
     }
 
     pub fn cx(&mut self, c: usize, t: usize) {
-        for i in 0..2 * self.n {
-            if self.x[i][c] && self.z[i][t] && (self.x[i][t] ^ self.z[i][c] ^ true) {
-                self.phase[i] = (self.phase[i] + 2) % 4;
-            }
-            self.x[i][t] ^= self.x[i][c];
-            self.z[i][c] ^= self.z[i][t];
+        for row in &mut self.rows {
+            row.cx(c, t);
         }
     }
 
     pub fn cz(&mut self, a: usize, b: usize) {
-        self.h(b);
-        self.cx(a, b);
-        self.h(b);
+        for row in &mut self.rows {
+            row.cz(a, b);
+        }
     }
 
     pub fn measure_z(&mut self, q: usize, rng: &mut impl Rng) -> (u8, bool) {
-        // Find a stabilizer row with X on q
-        let mut p = None;
-        for i in self.n..2 * self.n {
-            if self.x[i][q] {
-                p = Some(i);
-                break;
-            }
+        self.collapse_z(q, || coin(rng))
+    }
+
+    /// Measures `q` in the Z basis with the outcome `desired` (`true` for 1)
+    /// instead of a random draw, returning whether the outcome was random.
+    /// Fails, leaving the state unchanged, when the outcome is deterministic
+    /// and opposite.
+    pub fn measure_z_forced(&mut self, q: usize, desired: bool) -> Result<bool, String> {
+        let (bit, random) = self.collapse_z(q, || desired);
+        if (bit == 1) != desired {
+            return Err(format!(
+                "qubit {q} is deterministically measured as {bit}, cannot force {}",
+                desired as u8
+            ));
         }
+        Ok(random)
+    }
+
+    /// Z-basis measurement calling `random_outcome` only when the outcome
+    /// is not already determined.
+    pub(crate) fn collapse_z(
+        &mut self,
+        q: usize,
+        random_outcome: impl FnOnce() -> bool,
+    ) -> (u8, bool) {
+        // Find a stabilizer row with X on q
+        let has_x = |row: &PauliString| row.get(q).xz().0;
+        let p = (self.n..2 * self.n).find(|&i| has_x(&self.rows[i]));
 
         if let Some(p) = p {
             // Random outcome
-            let r: u8 = if rng.r#gen::<bool>() { 1 } else { 0 };
+            let r: u8 = if random_outcome() { 1 } else { 0 };
 
             // Clear X in column q for all rows except p
-            for i in 0..2 * self.n {
-                if i != p && self.x[i][q] {
-                    self.row_mult(i, p);
+            let pivot = self.rows[p].clone();
+            for (i, row) in self.rows.iter_mut().enumerate() {
+                if i != p && has_x(row) {
+                    *row *= &pivot;
                 }
             }
 
-            // Copy row p into corresponding destabilizer
-            let d = p - self.n;
-            self.copy_row(p, d);
-
-            // Set row p to Z_q with phase based on r
-            self.x[p].fill(false);
-            self.z[p].fill(false);
-            self.z[p][q] = true;
-            self.phase[p] = if r == 0 { 0 } else { 2 };
+            // Move row p into the corresponding destabilizer and set it to
+            // Z_q with phase based on r
+            let mut zq = single(self.n, q, Pauli::Z);
+            zq.set_negative(r == 1);
+            self.rows[p - self.n] = std::mem::replace(&mut self.rows[p], zq);
 
             return (r, true);
         }
 
-        // Deterministic outcome
-        // Build temporary row for Z_q
-        let mut temp_x = vec![false; self.n];
-        let mut temp_z = vec![false; self.n];
-        temp_z[q] = true;
-        let mut temp_phase: u8 = 0;
-
+        // Deterministic outcome: Z_q is the product of the stabilizers
+        // whose destabilizers have X on q
+        let mut temp = single(self.n, q, Pauli::Z);
         for i in 0..self.n {
-            if self.x[i][q] {
-                self.row_mult_temp(&mut temp_x, &mut temp_z, &mut temp_phase, i + self.n);
+            if has_x(&self.rows[i]) {
+                temp *= &self.rows[i + self.n];
             }
         }
 
-        let outcome = if temp_phase % 4 == 2 { 1 } else { 0 };
+        let outcome = if temp.is_negative() { 1 } else { 0 };
         (outcome, false)
     }
 
-    fn copy_row(&mut self, src: usize, dst: usize) {
-        let x_src = self.x[src].clone();
-        let z_src = self.z[src].clone();
-        self.x[dst].clone_from_slice(&x_src);
-        self.z[dst].clone_from_slice(&z_src);
-        self.phase[dst] = self.phase[src];
+    /// Expectation of `Z` on `q` without collapsing: `+1`, `-1`, or `0` when
+    /// a measurement would be random.
+    pub fn peek_z(&self, q: usize) -> i8 {
+        self.peek_single(q, Pauli::Z)
+    }
+
+    pub fn peek_x(&self, q: usize) -> i8 {
+        self.peek_single(q, Pauli::X)
+    }
+
+    pub fn peek_y(&self, q: usize) -> i8 {
+        self.peek_single(q, Pauli::Y)
+    }
+
+    /// The single-qubit Pauli `q` is an eigenstate of, signed by its
+    /// eigenvalue, or the identity when `q` is entangled or mixed.
+    pub fn peek_bloch(&self, q: usize) -> PauliString {
+        let mut out = PauliString::new(1);
+        for p in [Pauli::X, Pauli::Y, Pauli::Z] {
+            let e = self.peek_single(q, p);
+            if e != 0 {
+                out.set(0, p);
+                out.set_negative(e < 0);
+            }
+        }
+        out
+    }
+
+    /// Expectation of a Pauli product without collapsing: `+1` or `-1` when
+    /// the state is an eigenstate of it, otherwise `0`. Qubits past the end
+    /// of `p` are treated as identity. Fails if `p` is longer than the state
+    /// or has an imaginary phase.
+    pub fn peek_observable_expectation(&self, p: &PauliString) -> Result<i8, String> {
+        if p.len() > self.n {
+            return Err(format!(
+                "Pauli string on {} qubits is longer than the {}-qubit state",
+                p.len(),
+                self.n
+            ));
+        }
+        if !p.is_hermitian() {
+            return Err(format!("{p} is not Hermitian"));
+        }
+        Ok(self.expectation(p))
+    }
+
+    /// The stabilizer generators in reduced row echelon form (X before Z on
+    /// each qubit, in qubit order), so equal states give equal lists.
+    pub fn canonical_stabilizers(&self) -> Vec<PauliString> {
+        let mut rows = self.stabilizers();
+        reduce_rows(&mut rows, 0..self.n);
+        rows
+    }
+
+    /// Generators of the stabilizer group of the reduced state on `qubits`:
+    /// the stabilizers supported there, as strings over `qubits` in the
+    /// given order, in reduced row echelon form. Fails if a qubit is out of
+    /// range or listed twice.
+    pub fn reduced_stabilizers(&self, qubits: &[usize]) -> Result<Vec<PauliString>, String> {
+        let inside = self.subset_mask(qubits)?;
+        let mut rows = self.stabilizers();
+        let outside = (0..self.n).filter(|&q| !inside[q]);
+        let pivots = reduce_rows(&mut rows, outside);
+        let mut reduced: Vec<PauliString> = rows[pivots..]
+            .iter()
+            .map(|row| {
+                let mut p = PauliString::new(qubits.len());
+                for (j, &q) in qubits.iter().enumerate() {
+                    p.set(j, row.get(q));
+                }
+                p.set_phase(row.phase());
+                p
+            })
+            .collect();
+        reduce_rows(&mut reduced, 0..qubits.len());
+        Ok(reduced)
+    }
+
+    /// Entanglement entropy, in bits, between `qubits` and the rest of the
+    /// register: `|A|` minus the number of stabilizer generators supported
+    /// on `A`. Fails as [`reduced_stabilizers`](Self::reduced_stabilizers)
+    /// does.
+    pub fn entanglement_entropy(&self, qubits: &[usize]) -> Result<usize, String> {
+        Ok(qubits.len() - self.reduced_stabilizers(qubits)?.len())
+    }
+
+    /// Mutual information `S(A) + S(B) - S(AB)`, in bits, between disjoint
+    /// qubit sets. Fails if the sets overlap or a qubit is out of range.
+    pub fn mutual_information(&self, a: &[usize], b: &[usize]) -> Result<usize, String> {
+        let both: Vec<usize> = a.iter().chain(b).copied().collect();
+        let joint = self.entanglement_entropy(&both)?;
+        Ok(self.entanglement_entropy(a)? + self.entanglement_entropy(b)? - joint)
+    }
+
+    /// Builds the state stabilized by `stabilizers`, which must be `n`
+    /// commuting, independent Hermitian Pauli strings on `n` qubits.
+    /// Destabilizers are filled in by reducing the generators to single-qubit
+    /// `Z`s with Clifford gates and undoing those gates on `|0..0>`.
+    pub fn from_stabilizers(stabilizers: &[PauliString]) -> Result<Self, String> {
+        let n = stabilizers.len();
+        if let Some(p) = stabilizers.iter().find(|p| p.len() != n) {
+            return Err(format!("{n} stabilizers need {n} qubits each, got {p}"));
+        }
+        if let Some(p) = stabilizers.iter().find(|p| !p.is_hermitian()) {
+            return Err(format!("stabilizer {p} is not Hermitian"));
+        }
+        for (i, a) in stabilizers.iter().enumerate() {
+            if let Some(b) = stabilizers[i + 1..].iter().find(|b| !a.commutes(b)) {
+                return Err(format!("stabilizers {a} and {b} do not commute"));
+            }
+        }
+
+        let mut rows = stabilizers.to_vec();
+        let mut gates = Vec::new();
+        let mut used = vec![false; n];
+        for r in 0..n {
+            let Some(q) = (0..n).find(|&q| !used[q] && rows[r].get(q) != Pauli::I) else {
+                return Err("stabilizers are not independent".to_string());
+            };
+            // Turn every non-identity of row r on a free qubit into X, then fold
+            // them onto q with CX so the row becomes X_q, then Z_q.
+            for j in (0..n).filter(|&j| !used[j]) {
+                match rows[r].get(j) {
+                    Pauli::Z => apply_recorded(&mut rows, &mut gates, Gate::H(j)),
+                    Pauli::Y => apply_recorded(&mut rows, &mut gates, Gate::S(j)),
+                    _ => {}
+                }
+                if j != q && rows[r].get(j) == Pauli::X {
+                    apply_recorded(&mut rows, &mut gates, Gate::Cx(q, j));
+                }
+            }
+            apply_recorded(&mut rows, &mut gates, Gate::H(q));
+            if rows[r].is_negative() {
+                apply_recorded(&mut rows, &mut gates, Gate::X(q));
+            }
+            used[q] = true;
+            let pivot_row = rows[r].clone();
+            for (other, row) in rows.iter_mut().enumerate() {
+                if other != r && row.get(q) == Pauli::Z {
+                    *row *= &pivot_row;
+                }
+            }
+        }
+
+        let mut state = Self::new(n);
+        for gate in gates.into_iter().rev() {
+            match gate {
+                Gate::H(q) => state.h(q),
+                Gate::S(q) => state.s_dag(q),
+                Gate::X(q) => state.x_gate(q),
+                Gate::Cx(c, t) => state.cx(c, t),
+            }
+        }
+        Ok(state)
+    }
+
+    /// The state's amplitudes, qubit `q` being bit `q` of the basis index.
+    /// The global phase makes the first nonzero amplitude real and positive.
+    /// Takes `2^n` amplitudes, so fails past 24 qubits.
+    pub fn to_state_vector(&self) -> Result<Vec<Complex64>, String> {
+        if self.n > MAX_STATE_VECTOR_QUBITS {
+            return Err(format!("{} qubits is too many for a state vector", self.n));
+        }
+        // Project a basis state in the support onto the +1 eigenspace of
+        // every stabilizer; collapsing random outcomes to 0 finds such a state.
+        let mut probe = self.clone();
+        let basis: usize = (0..self.n)
+            .map(|q| (probe.collapse_z(q, || false).0 as usize) << q)
+            .sum();
+        let mut amps = vec![Complex64::new(0.0, 0.0); 1 << self.n];
+        amps[basis] = Complex64::new(1.0, 0.0);
+        for r in self.n..2 * self.n {
+            let moved = apply_to_amplitudes(&self.rows[r], &amps);
+            for (a, m) in amps.iter_mut().zip(moved) {
+                *a += m;
+            }
+        }
+        let norm = amps.iter().map(|a| a.norm_sqr()).sum::<f64>().sqrt();
+        let first = *amps
+            .iter()
+            .find(|a| a.norm() > TOLERANCE)
+            .expect("projected state is nonzero");
+        let scale = first.conj() / (first.norm() * norm);
+        Ok(amps.iter().map(|a| a * scale).collect())
+    }
+
+    /// Recognizes a stabilizer state from its amplitudes (in the layout of
+    /// [`to_state_vector`](Self::to_state_vector), any norm and global
+    /// phase), failing on anything that is not a stabilizer state.
+    pub fn from_state_vector(amps: &[Complex64]) -> Result<Self, String> {
+        if !amps.len().is_power_of_two() {
+            return Err(format!("{} amplitudes is not a power of two", amps.len()));
+        }
+        let n = amps.len().trailing_zeros() as usize;
+        if n > MAX_STATE_VECTOR_QUBITS {
+            return Err(format!("{n} qubits is too many for a state vector"));
+        }
+        let norm = amps.iter().map(|a| a.norm_sqr()).sum::<f64>().sqrt();
+        if !(norm > 0.0 && norm.is_finite()) {
+            return Err("state vector has no usable norm".to_string());
+        }
+        let amps: Vec<Complex64> = amps.iter().map(|a| a / norm).collect();
+        let not_stabilizer = || "not a stabilizer state".to_string();
+
+        // The support of a stabilizer state is b0 + V for a subspace V.
+        let support: Vec<usize> = (0..amps.len())
+            .filter(|&j| amps[j].norm() > TOLERANCE)
+            .collect();
+        let b0 = support[0];
+        let mut span: Vec<usize> = Vec::new();
+        for &j in &support {
+            let mut v = j ^ b0;
+            for &b in &span {
+                v = v.min(v ^ b);
+            }
+            if v != 0 {
+                span.push(v);
+                span.sort_unstable_by(|a, b| b.cmp(a));
+            }
+        }
+        if support.len() != 1 << span.len() {
+            return Err(not_stabilizer());
+        }
+        // Reduce so each basis vector owns its leading (pivot) bit.
+        let pivot = |v: usize| 1usize << (usize::BITS - 1 - v.leading_zeros());
+        for i in 0..span.len() {
+            for k in 0..span.len() {
+                if k != i && span[k] & pivot(span[i]) != 0 {
+                    span[k] ^= span[i];
+                }
+            }
+        }
+
+        let mut stabilizers = Vec::new();
+        // Z-type stabilizers: Z^u for every u orthogonal to V, u ranging
+        // over the non-pivot qubits with the pivots fixed to match.
+        for q in (0..n).filter(|&q| span.iter().all(|&v| pivot(v) != 1 << q)) {
+            let mut u = 1usize << q;
+            for &v in &span {
+                if v >> q & 1 == 1 {
+                    u |= pivot(v);
+                }
+            }
+            let mut p = PauliString::new(n);
+            for k in (0..n).filter(|&k| u >> k & 1 == 1) {
+                p.set(k, Pauli::Z);
+            }
+            p.set_negative((u & b0).count_ones() % 2 == 1);
+            stabilizers.push(p);
+        }
+        // X-type stabilizers c X^v Z^w, one per basis vector v, with w read
+        // off the amplitude ratios across the support.
+        for &v in &span {
+            let c = amps[b0 ^ v] / amps[b0];
+            let mut w = 0usize;
+            for &other in &span {
+                let ratio = amps[b0 ^ other ^ v] / amps[b0 ^ other] / c;
+                if (ratio + 1.0).norm() < TOLERANCE {
+                    w |= pivot(other);
+                } else if (ratio - 1.0).norm() > TOLERANCE {
+                    return Err(not_stabilizer());
+                }
+            }
+            // The ratios were taken relative to b0, which carries (-1)^(w.b0);
+            // X^v Z^w is (-i)^|v&w| times the string with Y where both are set.
+            let c = c * if (w & b0).count_ones() % 2 == 1 {
+                -1.0
+            } else {
+                1.0
+            };
+            let c = c * Complex64::new(0.0, -1.0).powu((v & w).count_ones());
+            let mut p = PauliString::new(n);
+            for k in 0..n {
+                p.set(k, Pauli::from_xz(v >> k & 1 == 1, w >> k & 1 == 1));
+            }
+            if (c - 1.0).norm() < TOLERANCE {
+                p.set_negative(false);
+            } else if (c + 1.0).norm() < TOLERANCE {
+                p.set_negative(true);
+            } else {
+                return Err(not_stabilizer());
+            }
+            stabilizers.push(p);
+        }
+
+        let state = Self::from_stabilizers(&stabilizers).map_err(|_| not_stabilizer())?;
+        let overlap: Complex64 = state
+            .to_state_vector()?
+            .iter()
+            .zip(&amps)
+            .map(|(a, b)| a.conj() * b)
+            .sum();
+        if (overlap.norm() - 1.0).abs() > TOLERANCE {
+            return Err(not_stabilizer());
+        }
+        Ok(state)
+    }
+
+    /// Applies a Pauli gate to `q`.
+    pub fn apply_pauli(&mut self, q: usize, p: Pauli) {
+        match p {
+            Pauli::I => {}
+            Pauli::X => self.x_gate(q),
+            Pauli::Y => self.y_gate(q),
+            Pauli::Z => self.z_gate(q),
+        }
+    }
+
+    fn stabilizers(&self) -> Vec<PauliString> {
+        self.rows[self.n..].to_vec()
+    }
+
+    /// Membership of each qubit in `qubits`, which must be distinct.
+    fn subset_mask(&self, qubits: &[usize]) -> Result<Vec<bool>, String> {
+        let mut inside = vec![false; self.n];
+        for &q in qubits {
+            if q >= self.n {
+                return Err(format!("qubit {q} out of range"));
+            }
+            if inside[q] {
+                return Err(format!("qubit {q} listed twice"));
+            }
+            inside[q] = true;
+        }
+        Ok(inside)
+    }
+
+    fn peek_single(&self, q: usize, pauli: Pauli) -> i8 {
+        let mut p = PauliString::new(self.n);
+        p.set(q, pauli);
+        self.expectation(&p)
     }
 
-    fn row_mult(&mut self, h: usize, i: usize) {
-        let mut ph = 0u8;
-        for q in 0..self.n {
-            let (x3, z3, k) = mul_pauli(self.x[h][q], self.z[h][q], self.x[i][q], self.z[i][q]);
-            self.x[h][q] = x3;
-            self.z[h][q] = z3;
-            ph = (ph + k) % 4;
+    fn expectation(&self, p: &PauliString) -> i8 {
+        let (destabilizers, stabilizers) = self.rows.split_at(self.n);
+        if stabilizers.iter().any(|s| !s.commutes(p)) {
+            return 0;
+        }
+        // p commutes with every stabilizer, so it is (up to sign) the product
+        // of the stabilizers whose destabilizers it anticommutes with.
+        let mut product = PauliString::new(self.n);
+        for (d, s) in destabilizers.iter().zip(stabilizers) {
+            if !d.commutes(p) {
+                product *= s;
+            }
+        }
+        if product.is_negative() == p.is_negative() {
+            1
+        } else {
+            -1
         }
-        self.phase[h] = (self.phase[h] + self.phase[i] + ph) % 4;
     }
+}
 
-    fn row_mult_temp(
-        &self,
-        x: &mut [bool],
-        z: &mut [bool],
-        phase: &mut u8,
-        i: usize,
-    ) {
-        let mut ph = 0u8;
-        for q in 0..self.n {
-            let (x3, z3, k) = mul_pauli(x[q], z[q], self.x[i][q], self.z[i][q]);
-            x[q] = x3;
-            z[q] = z3;
-            ph = (ph + k) % 4;
+/// The string with `p` on qubit `q` and identity on the other `n - 1`.
+fn single(n: usize, q: usize, p: Pauli) -> PauliString {
+    let mut out = PauliString::new(n);
+    out.set(q, p);
+    out
+}
+
+/// Gaussian elimination of `rows` on the X then Z bit of each of `qubits`
+/// in turn. Pivot rows move to the front; the count is returned, and the
+/// remaining rows are identity on every eliminated qubit.
+fn reduce_rows(rows: &mut [PauliString], qubits: impl IntoIterator<Item = usize>) -> usize {
+    let mut min_pivot = 0;
+    for q in qubits {
+        for want_x in [true, false] {
+            let has = |p: &PauliString| {
+                let (x, z) = p.get(q).xz();
+                if want_x { x } else { z }
+            };
+            let Some(pivot) = (min_pivot..rows.len()).find(|&r| has(&rows[r])) else {
+                continue;
+            };
+            let pivot_row = rows[pivot].clone();
+            for (r, row) in rows.iter_mut().enumerate() {
+                if r != pivot && has(row) {
+                    *row *= &pivot_row;
+                }
+            }
+            rows.swap(pivot, min_pivot);
+            min_pivot += 1;
         }
-        *phase = (*phase + self.phase[i] + ph) % 4;
     }
+    min_pivot
 }
 
-fn mul_pauli(x1: bool, z1: bool, x2: bool, z2: bool) -> (bool, bool, u8) {
-    let (p1, p2) = ((x1, z1), (x2, z2));
-    // (x,z) encoding: I(0,0), X(1,0), Z(0,1), Y(1,1)
-    match (p1, p2) {
-        ((false, false), _) => (x2, z2, 0),
-        (_, (false, false)) => (x1, z1, 0),
-        ((true, false), (true, false)) => (false, false, 0), // X*X=I
-        ((false, true), (false, true)) => (false, false, 0), // Z*Z=I
-        ((true, true), (true, true)) => (false, false, 0),   // Y*Y=I
-        ((true, false), (false, true)) => (true, true, 1),   // X*Z=iY
-        ((false, true), (true, false)) => (true, true, 3),   // Z*X=-iY
-        ((true, false), (true, true)) => (false, true, 1),   // X*Y=iZ
-        ((true, true), (true, false)) => (false, true, 3),   // Y*X=-iZ
-        ((false, true), (true, true)) => (true, false, 3),   // Z*Y=-iX
-        ((true, true), (false, true)) => (true, false, 1),   // Y*Z=iX
+/// `P|psi>` for amplitudes laid out as in `to_state_vector`.
+fn apply_to_amplitudes(p: &PauliString, amps: &[Complex64]) -> Vec<Complex64> {
+    let (mut xs, mut zs, mut k) = (0usize, 0usize, p.phase() as u32);
+    for (q, pauli) in p.iter().enumerate() {
+        let (x, z) = pauli.xz();
+        xs |= (x as usize) << q;
+        zs |= (z as usize) << q;
+        // Y = i X Z, with Z acting first.
+        k += (x && z) as u32;
     }
+    let factor = Complex64::i().powu(k % 4);
+    let mut out = vec![Complex64::new(0.0, 0.0); amps.len()];
+    for (j, a) in amps.iter().enumerate() {
+        let sign = if (j & zs).count_ones() % 2 == 1 {
+            -1.0
+        } else {
+            1.0
+        };
+        out[j ^ xs] = a * factor * sign;
+    }
+    out
+}
+
+fn apply_recorded(rows: &mut [PauliString], gates: &mut Vec<Gate>, gate: Gate) {
+    for p in rows.iter_mut() {
+        match gate {
+            Gate::H(q) => p.h(q),
+            Gate::S(q) => p.s(q),
+            Gate::X(q) => p.x_gate(q),
+            Gate::Cx(c, t) => p.cx(c, t),
+        }
+    }
+    gates.push(gate);
+}
+
+/// Gates recorded while reducing stabilizers in `from_stabilizers`.
+#[derive(Clone, Copy)]
+enum Gate {
+    H(usize),
+    S(usize),
+    X(usize),
+    Cx(usize, usize),
 }
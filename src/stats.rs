@@ -0,0 +1,78 @@
+use std::collections::BTreeMap;
+
+use crate::gates::is_measurement;
+use crate::ir::{StimInstr, StimTarget};
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CircuitStats {
+    pub num_qubits: usize,
+    pub num_measurements: u64,
+    pub num_detectors: u64,
+    pub num_observables: u64,
+    pub num_ticks: u64,
+    pub num_sweep_bits: usize,
+    pub max_lookback: usize,
+    pub gate_counts: BTreeMap<String, u64>,
+}
+
+/// Computes circuit statistics without simulating. `REPEAT` bodies are
+/// analysed once and their counts scaled by the repetition count.
+pub fn stats(instrs: &[StimInstr]) -> CircuitStats {
+    let mut out = CircuitStats::default();
+    for instr in instrs {
+        match instr {
+            StimInstr::Op {
+                name,
+                args,
+                targets,
+                ..
+            } => {
+                *out.gate_counts.entry(name.clone()).or_insert(0) += 1;
+                for t in targets {
+                    match t {
+                        StimTarget::Qubit(q) | StimTarget::QubitInv(q) => {
+                            out.num_qubits = out.num_qubits.max(*q as usize + 1);
+                        }
+                        StimTarget::Rec(o) => {
+                            out.max_lookback = out.max_lookback.max(o.unsigned_abs() as usize);
+                        }
+                        StimTarget::Sweep(k) => {
+                            out.num_sweep_bits = out.num_sweep_bits.max(*k as usize + 1);
+                        }
+                    }
+                }
+                match name.as_str() {
+                    "DETECTOR" => out.num_detectors += 1,
+                    "OBSERVABLE_INCLUDE" => {
+                        let index = args.first().copied().unwrap_or(0.0) as u64;
+                        out.num_observables = out.num_observables.max(index + 1);
+                    }
+                    "TICK" => out.num_ticks += 1,
+                    n if is_measurement(n) => out.num_measurements += targets.len() as u64,
+                    _ => {}
+                }
+            }
+            StimInstr::Repeat { count, body } => {
+                let inner = stats(body);
+                out.num_qubits = out.num_qubits.max(inner.num_qubits);
+                out.num_measurements = out
+                    .num_measurements
+                    .saturating_add(inner.num_measurements.saturating_mul(*count));
+                out.num_detectors = out
+                    .num_detectors
+                    .saturating_add(inner.num_detectors.saturating_mul(*count));
+                out.num_observables = out.num_observables.max(inner.num_observables);
+                out.num_ticks = out
+                    .num_ticks
+                    .saturating_add(inner.num_ticks.saturating_mul(*count));
+                out.num_sweep_bits = out.num_sweep_bits.max(inner.num_sweep_bits);
+                out.max_lookback = out.max_lookback.max(inner.max_lookback);
+                for (name, n) in inner.gate_counts {
+                    let entry = out.gate_counts.entry(name).or_insert(0);
+                    *entry = entry.saturating_add(n.saturating_mul(*count));
+                }
+            }
+        }
+    }
+    out
+}
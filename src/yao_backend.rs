@@ -0,0 +1,154 @@
+use rand::Rng;
+use yao_rs::measure::measure_and_collapse;
+use yao_rs::{Circuit as YaoCircuit, Gate, PositionedGate, State, apply, control, put};
+
+use crate::compiled::Basis;
+use crate::executor::Executor;
+use crate::pauli::Pauli;
+use crate::sim::backend::SimulatorBackend;
+
+/// A dense `2^n` state vector evolved by yao-rs, holding at most
+/// [`MAX_QUBITS`](SimulatorBackend::MAX_QUBITS) qubits.
+///
+/// The register is fixed at construction: unlike the tableau, it does not
+/// grow, so [`Executor::run_on`] fails on a state narrower than the circuit.
+#[derive(Debug, Clone)]
+pub struct YaoState {
+    n: usize,
+    state: State,
+}
+
+impl YaoState {
+    pub fn new(n: usize) -> Self {
+        Self {
+            n,
+            state: State::zero_state(&vec![2; n]),
+        }
+    }
+
+    pub fn num_qubits(&self) -> usize {
+        self.n
+    }
+
+    pub fn state(&self) -> &State {
+        &self.state
+    }
+
+    pub fn h(&mut self, q: usize) {
+        self.apply(vec![put(vec![q], Gate::H)]);
+    }
+
+    pub fn s(&mut self, q: usize) {
+        self.apply(vec![put(vec![q], Gate::S)]);
+    }
+
+    pub fn s_dag(&mut self, q: usize) {
+        self.apply(vec![put(vec![q], Gate::S); 3]);
+    }
+
+    pub fn x_gate(&mut self, q: usize) {
+        self.apply(vec![put(vec![q], Gate::X)]);
+    }
+
+    pub fn y_gate(&mut self, q: usize) {
+        self.apply(vec![put(vec![q], Gate::Y)]);
+    }
+
+    pub fn z_gate(&mut self, q: usize) {
+        self.apply(vec![put(vec![q], Gate::Z)]);
+    }
+
+    /// Panics if `c == t`; compiled circuits never pair a qubit with itself.
+    pub fn cx(&mut self, c: usize, t: usize) {
+        assert_ne!(c, t, "cx needs two distinct qubits");
+        self.apply(vec![control(vec![c], vec![t], Gate::X)]);
+    }
+
+    /// Panics if `c == t`; compiled circuits never pair a qubit with itself.
+    pub fn cz(&mut self, c: usize, t: usize) {
+        assert_ne!(c, t, "cz needs two distinct qubits");
+        self.apply(vec![control(vec![c], vec![t], Gate::Z)]);
+    }
+
+    pub fn apply_pauli(&mut self, q: usize, p: Pauli) {
+        match p {
+            Pauli::I => {}
+            Pauli::X => self.x_gate(q),
+            Pauli::Y => self.y_gate(q),
+            Pauli::Z => self.z_gate(q),
+        }
+    }
+
+    fn measure_z(&mut self, q: usize, rng: &mut impl Rng) -> bool {
+        measure_and_collapse(&mut self.state, Some(&[q]), rng)[0] == 1
+    }
+
+    fn apply(&mut self, gates: Vec<PositionedGate>) {
+        // Compilation rejects repeated pair targets and the executor sizes the
+        // register to the circuit, so yao-rs only sees valid locations.
+        let circuit = YaoCircuit::new(vec![2; self.n], gates).expect("gate locations are valid");
+        self.state = apply(&circuit, &self.state);
+    }
+}
+
+/// Runs compiled circuits on [`YaoState`], a dense reference for checking
+/// the stabilizer executor on small circuits. Noise reads the rng exactly as
+/// the tableau executor does, but measurements draw through yao-rs, so
+/// seeded shots only agree in distribution.
+pub type YaoExecutor = Executor<YaoState>;
+
+impl SimulatorBackend for YaoState {
+    const MAX_QUBITS: usize = 20;
+
+    fn with_qubits(n: usize) -> Self {
+        Self::new(n)
+    }
+
+    fn num_qubits(&self) -> usize {
+        self.num_qubits()
+    }
+
+    fn h(&mut self, q: usize) {
+        self.h(q);
+    }
+
+    fn s(&mut self, q: usize) {
+        self.s(q);
+    }
+
+    fn s_dag(&mut self, q: usize) {
+        self.s_dag(q);
+    }
+
+    fn cx(&mut self, c: usize, t: usize) {
+        self.cx(c, t);
+    }
+
+    fn cz(&mut self, c: usize, t: usize) {
+        self.cz(c, t);
+    }
+
+    fn apply_pauli(&mut self, q: usize, p: Pauli) {
+        self.apply_pauli(q, p);
+    }
+
+    fn measure(&mut self, basis: Basis, q: usize, rng: &mut impl Rng) -> bool {
+        match basis {
+            Basis::Z => self.measure_z(q, rng),
+            Basis::X => {
+                self.h(q);
+                let bit = self.measure_z(q, rng);
+                self.h(q);
+                bit
+            }
+            Basis::Y => {
+                self.s_dag(q);
+                self.h(q);
+                let bit = self.measure_z(q, rng);
+                self.h(q);
+                self.s(q);
+                bit
+            }
+        }
+    }
+}
@@ -0,0 +1,106 @@
+use rand::Rng;
+use rstim::compiled::Basis;
+use rstim::executor::Executor;
+use rstim::parser::parse_lines;
+use rstim::pauli::Pauli;
+use rstim::seed::ShotRng;
+use rstim::sim::backend::SimulatorBackend;
+use rstim::sim::tableau::StabilizerState;
+
+/// Wraps the tableau and logs every call the executor makes.
+struct Logging {
+    inner: StabilizerState,
+    log: Vec<String>,
+}
+
+impl SimulatorBackend for Logging {
+    const MAX_QUBITS: usize = 3;
+
+    fn with_qubits(n: usize) -> Self {
+        Self {
+            inner: StabilizerState::new(n),
+            log: Vec::new(),
+        }
+    }
+
+    fn num_qubits(&self) -> usize {
+        self.inner.num_qubits()
+    }
+
+    fn h(&mut self, q: usize) {
+        self.log.push(format!("H {q}"));
+        self.inner.h(q);
+    }
+
+    fn s(&mut self, q: usize) {
+        self.log.push(format!("S {q}"));
+        self.inner.s(q);
+    }
+
+    fn cx(&mut self, c: usize, t: usize) {
+        self.log.push(format!("CX {c} {t}"));
+        self.inner.cx(c, t);
+    }
+
+    fn cz(&mut self, c: usize, t: usize) {
+        self.log.push(format!("CZ {c} {t}"));
+        self.inner.cz(c, t);
+    }
+
+    fn apply_pauli(&mut self, q: usize, p: Pauli) {
+        self.log.push(format!("{p:?} {q}"));
+        self.inner.apply_pauli(q, p);
+    }
+
+    fn measure(&mut self, basis: Basis, q: usize, rng: &mut impl Rng) -> bool {
+        self.log.push(format!("M{basis:?} {q}"));
+        SimulatorBackend::measure(&mut self.inner, basis, q, rng)
+    }
+}
+
+#[test]
+fn custom_backend_sees_primitive_operations() {
+    let text = "H 0\nCX 0 1\nCX sweep[0] 1\nY 1\nMX 0\nR 1\nM 1";
+    let mut ex = Executor::<Logging>::compile(parse_lines(text).unwrap()).unwrap();
+    ex.set_sweep_bits(vec![true]);
+    let mut state = Logging::with_qubits(2);
+    let out = ex.run_on(&mut state, &mut ShotRng::new(0, 0)).unwrap();
+    assert_eq!(out.measurements.len(), 2);
+    assert!(!out.measurements[1]);
+    assert_eq!(
+        state.log[..6],
+        ["H 0", "CX 0 1", "X 1", "Y 1", "MX 0", "MZ 1"]
+    );
+}
+
+#[test]
+fn custom_backend_matches_the_tableau_executor() {
+    let text = "H 0\nCX 0 1 1 2\nDEPOLARIZE1(0.2) 0 1 2\nMY 0\nM 1 2\nDETECTOR rec[-1] rec[-2]";
+    let mut logged = Executor::<Logging>::compile(parse_lines(text).unwrap()).unwrap();
+    let mut plain = Executor::from_instrs(parse_lines(text).unwrap()).unwrap();
+    for shot in 0..50 {
+        let a = logged.run(&mut ShotRng::new(7, shot)).unwrap();
+        let b = plain.run(&mut ShotRng::new(7, shot)).unwrap();
+        assert_eq!(a.measurements, b.measurements);
+        assert_eq!(a.detectors, b.detectors);
+    }
+}
+
+#[test]
+fn backend_limits_and_state_size_are_checked() {
+    assert!(Executor::<Logging>::compile(parse_lines("H 3").unwrap()).is_err());
+    let mut ex = Executor::<Logging>::compile(parse_lines("H 2").unwrap()).unwrap();
+    let mut small = Logging::with_qubits(1);
+    assert!(ex.run_on(&mut small, &mut ShotRng::new(0, 0)).is_err());
+}
+
+#[test]
+fn state_grows_when_an_op_first_needs_it() {
+    let mut ex = Executor::<Logging>::compile(parse_lines("H 0\nM 0\nH 2").unwrap()).unwrap();
+    let mut small = Logging::with_qubits(1);
+    let Err(err) = ex.run_on(&mut small, &mut ShotRng::new(0, 0)) else {
+        panic!("grew a backend that cannot grow");
+    };
+    assert!(err.contains("cannot grow to 3"), "{err}");
+    assert_eq!(small.log, ["H 0", "MZ 0"]);
+}
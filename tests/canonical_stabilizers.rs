@@ -0,0 +1,65 @@
+mod clifford_helpers;
+use clifford_helpers::random_state;
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use rstim::pauli::PauliString;
+use rstim::sim::tableau::StabilizerState;
+
+fn strings(st: &StabilizerState) -> Vec<String> {
+    st.canonical_stabilizers()
+        .iter()
+        .map(|p| p.to_string())
+        .collect()
+}
+
+fn paulis(list: &[&str]) -> Vec<PauliString> {
+    list.iter().map(|p| p.parse().unwrap()).collect()
+}
+
+#[test]
+fn bell_pairs_from_different_circuits_agree() {
+    let mut a = StabilizerState::new(2);
+    a.h(0);
+    a.cx(0, 1);
+    let mut b = StabilizerState::new(2);
+    b.h(1);
+    b.cx(1, 0);
+    assert_eq!(strings(&a), vec!["+XX", "+ZZ"]);
+    assert_eq!(strings(&a), strings(&b));
+    b.z_gate(0);
+    assert_eq!(strings(&b), vec!["-XX", "+ZZ"]);
+}
+
+#[test]
+fn builds_state_from_stabilizers() {
+    let st = StabilizerState::from_stabilizers(&paulis(&["+XX", "-ZZ"])).unwrap();
+    let xx = "XX".parse().unwrap();
+    let zz = "ZZ".parse().unwrap();
+    assert_eq!(st.peek_observable_expectation(&xx).unwrap(), 1);
+    assert_eq!(st.peek_observable_expectation(&zz).unwrap(), -1);
+    assert_eq!(strings(&st), vec!["+XX", "-ZZ"]);
+}
+
+#[test]
+fn round_trips_random_states() {
+    let mut rng = StdRng::seed_from_u64(7);
+    for n in [2, 3, 5] {
+        for _ in 0..20 {
+            let st = random_state(&mut rng, n, 40);
+            let canon = st.canonical_stabilizers();
+            let rebuilt = StabilizerState::from_stabilizers(&canon).unwrap();
+            assert_eq!(rebuilt.canonical_stabilizers(), canon);
+            for p in &canon {
+                assert_eq!(rebuilt.peek_observable_expectation(p).unwrap(), 1);
+            }
+        }
+    }
+}
+
+#[test]
+fn rejects_invalid_generator_sets() {
+    assert!(StabilizerState::from_stabilizers(&paulis(&["X_", "Z_"])).is_err());
+    assert!(StabilizerState::from_stabilizers(&paulis(&["ZZ", "-ZZ"])).is_err());
+    assert!(StabilizerState::from_stabilizers(&paulis(&["ZZ", "ZZ"])).is_err());
+    assert!(StabilizerState::from_stabilizers(&paulis(&["ZZ"])).is_err());
+}
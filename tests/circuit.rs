@@ -0,0 +1,61 @@
+use rstim::circuit::Circuit;
+use rstim::ir::StimInstr;
+
+#[test]
+fn add_fuses_boundary_instructions() {
+    let a = Circuit::parse("R 0\nH 0\n").unwrap();
+    let b = Circuit::parse("H 1\nM 0 1\n").unwrap();
+    let c = &a + &b;
+    assert_eq!(c, Circuit::parse("R 0\nH 0 1\nM 0 1\n").unwrap());
+
+    let mut d = a.clone();
+    d += b;
+    assert_eq!(d, c);
+}
+
+#[test]
+fn add_keeps_detectors_separate() {
+    let a = Circuit::parse("M 0\nDETECTOR rec[-1]\n").unwrap();
+    let b = Circuit::parse("DETECTOR rec[-1]\n").unwrap();
+    assert_eq!((a + b).len(), 3);
+}
+
+#[test]
+fn add_only_fuses_known_gates() {
+    let a = Circuit::parse("FOO 0\n").unwrap();
+    let b = Circuit::parse("FOO 1\n").unwrap();
+    assert_eq!((a + b).len(), 2);
+}
+
+#[test]
+#[allow(clippy::erasing_op)]
+fn mul_wraps_in_repeat() {
+    let body = Circuit::parse("H 0\n").unwrap();
+    let c = &body * 5;
+    match &c[0] {
+        StimInstr::Repeat { count, body } => {
+            assert_eq!(*count, 5);
+            assert_eq!(body.len(), 1);
+        }
+        _ => panic!("expected Repeat"),
+    }
+    assert!((&body * 0).is_empty());
+    assert_eq!(&body * 1, body);
+}
+
+#[test]
+fn slices_top_level_instructions() {
+    let c = Circuit::parse("H 0\nX 1\nM 0\nM 1\n").unwrap();
+    assert_eq!(c[1].name(), Some("X"));
+    assert_eq!(c[1..3].len(), 2);
+    assert_eq!(c.slice(2..), Circuit::parse("M 0\nM 1\n").unwrap());
+}
+
+#[test]
+fn approx_eq_tolerates_arg_noise() {
+    let a = Circuit::parse("X_ERROR(0.1) 0\n").unwrap();
+    let b = Circuit::parse("X_ERROR(0.1000001) 0\n").unwrap();
+    assert_ne!(a, b);
+    assert!(a.approx_eq(&b, 1e-6));
+    assert!(!a.approx_eq(&b, 1e-9));
+}
@@ -0,0 +1,26 @@
+mod cli_helpers;
+use cli_helpers::rstim;
+
+#[test]
+fn writes_detector_error_model() {
+    let program = "X_ERROR(0.125) 0\nM 0\nDETECTOR rec[-1]\n";
+    let (ok, out) = rstim(&["analyze_errors"], program);
+    assert!(ok);
+    assert_eq!(out, "error(0.125) D0\n");
+}
+
+#[test]
+fn fails_on_undecomposable_error() {
+    let program = "X_ERROR(0.1) 0\nCX 0 1 0 2\nM 0 1 2\nDETECTOR rec[-1]\nDETECTOR rec[-2]\nDETECTOR rec[-3]\n";
+    let (ok, _) = rstim(&["analyze_errors", "--decompose_errors"], program);
+    assert!(!ok);
+    let (ok, _) = rstim(
+        &[
+            "analyze_errors",
+            "--decompose_errors",
+            "--ignore_decomposition_failures",
+        ],
+        program,
+    );
+    assert!(ok);
+}
@@ -0,0 +1,45 @@
+mod cli_helpers;
+use cli_helpers::rstim;
+
+const PROGRAM: &str =
+    "X_ERROR(1) 1\nM 0 1\nDETECTOR rec[-2]\nDETECTOR rec[-1]\nOBSERVABLE_INCLUDE(1) rec[-1]\n";
+
+#[test]
+fn writes_detection_events() {
+    let (ok, out) = rstim(&["detect", "--shots", "2"], PROGRAM);
+    assert!(ok);
+    assert_eq!(out, "01\n01\n");
+}
+
+#[test]
+fn appends_observables() {
+    let (ok, out) = rstim(
+        &["detect", "--append_observables", "--out_format", "dets"],
+        PROGRAM,
+    );
+    assert!(ok);
+    assert_eq!(out, "shot D1 L1\n");
+}
+
+#[test]
+fn writes_observables_to_separate_file() {
+    let dir = std::env::temp_dir().join(format!("rstim_cli_detect_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let obs = dir.join("obs.txt");
+    let (ok, out) = rstim(
+        &[
+            "detect",
+            "--shots",
+            "2",
+            "--obs_out",
+            obs.to_str().unwrap(),
+            "--obs_out_format",
+            "hits",
+        ],
+        PROGRAM,
+    );
+    assert!(ok);
+    assert_eq!(out, "01\n01\n");
+    assert_eq!(std::fs::read_to_string(&obs).unwrap(), "1\n1\n");
+    std::fs::remove_dir_all(&dir).unwrap();
+}
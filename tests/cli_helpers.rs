@@ -0,0 +1,20 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// Runs the `rstim` binary, feeding `stdin`, and returns (success, stdout).
+pub fn rstim(args: &[&str], stdin: &str) -> (bool, String) {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rstim"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    // The binary may reject its arguments and exit before reading stdin.
+    let _ = child.stdin.take().unwrap().write_all(stdin.as_bytes());
+    let out = child.wait_with_output().unwrap();
+    (
+        out.status.success(),
+        String::from_utf8_lossy(&out.stdout).into_owned(),
+    )
+}
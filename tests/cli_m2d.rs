@@ -0,0 +1,56 @@
+mod cli_helpers;
+use cli_helpers::rstim;
+
+fn write_circuit(name: &str, text: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("rstim_cli_m2d_{}_{name}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("circuit.stim");
+    std::fs::write(&path, text).unwrap();
+    path
+}
+
+#[test]
+fn converts_measurement_records() {
+    let path = write_circuit(
+        "basic",
+        "X 1\nM 0 1\nDETECTOR rec[-2]\nDETECTOR rec[-1]\nOBSERVABLE_INCLUDE(0) rec[-1]\n",
+    );
+    let (ok, out) = rstim(
+        &[
+            "m2d",
+            "--circuit",
+            path.to_str().unwrap(),
+            "--append_observables",
+        ],
+        "01\n10\n",
+    );
+    assert!(ok);
+    assert_eq!(out, "000\n111\n");
+    std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+}
+
+#[test]
+fn reads_sweep_data() {
+    let path = write_circuit("sweep", "CX sweep[0] 0\nM 0\nDETECTOR rec[-1]\n");
+    let sweep = path.with_file_name("sweep.01");
+    std::fs::write(&sweep, "1\n0\n").unwrap();
+    let (ok, out) = rstim(
+        &[
+            "m2d",
+            "--circuit",
+            path.to_str().unwrap(),
+            "--sweep",
+            sweep.to_str().unwrap(),
+        ],
+        "1\n1\n",
+    );
+    assert!(ok);
+    assert_eq!(out, "0\n1\n");
+    std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+}
+
+#[test]
+fn requires_circuit() {
+    let (ok, _) = rstim(&["m2d"], "0\n");
+    assert!(!ok);
+}
@@ -0,0 +1,66 @@
+mod cli_helpers;
+use cli_helpers::rstim;
+
+#[test]
+fn samples_from_stdin() {
+    let (ok, out) = rstim(&["sample", "--shots", "3"], "X 0\nM 0 1\n");
+    assert!(ok);
+    assert_eq!(out, "10\n10\n10\n");
+}
+
+#[test]
+fn writes_requested_format_to_file() {
+    let dir = std::env::temp_dir().join(format!("rstim_cli_sample_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let circuit = dir.join("c.stim");
+    let samples = dir.join("s.txt");
+    std::fs::write(&circuit, "X 2\nM 0 1 2\n").unwrap();
+    let (ok, _) = rstim(
+        &[
+            "sample",
+            "--shots=2",
+            "--seed",
+            "5",
+            "--out-format",
+            "hits",
+            "--in",
+            circuit.to_str().unwrap(),
+            "--out",
+            samples.to_str().unwrap(),
+        ],
+        "",
+    );
+    assert!(ok);
+    assert_eq!(std::fs::read_to_string(&samples).unwrap(), "2\n2\n");
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn seed_makes_output_reproducible() {
+    let program = "H 0 1 2 3 4 5 6 7\nM 0 1 2 3 4 5 6 7\n";
+    let (_, a) = rstim(&["sample", "--shots", "20", "--seed", "7"], program);
+    let (_, b) = rstim(&["sample", "--shots", "20", "--seed", "7"], program);
+    assert_eq!(a, b);
+}
+
+#[test]
+fn rejects_unknown_flags() {
+    let (ok, _) = rstim(&["sample", "--bogus", "1"], "M 0\n");
+    assert!(!ok);
+}
+
+#[test]
+fn prints_version_without_a_command() {
+    let (ok, out) = rstim(&[], "");
+    assert!(ok);
+    assert!(out.starts_with("rstim "), "{out}");
+}
+
+#[test]
+fn rejects_path_flags_without_a_value() {
+    let (ok, out) = rstim(&["sample", "--out"], "M 0\n");
+    assert!(!ok);
+    assert_eq!(out, "");
+    let (ok, _) = rstim(&["sample", "--in", "--shots", "2"], "M 0\n");
+    assert!(!ok);
+}
@@ -0,0 +1,18 @@
+mod cli_helpers;
+use cli_helpers::rstim;
+
+#[test]
+fn samples_detectors_and_observables() {
+    let (ok, out) = rstim(
+        &["sample_dem", "--shots", "2", "--append_observables"],
+        "error(1) D1 L0\nerror(0) D0\n",
+    );
+    assert!(ok);
+    assert_eq!(out, "011\n011\n");
+}
+
+#[test]
+fn rejects_bad_model() {
+    let (ok, _) = rstim(&["sample_dem"], "error(2) D0\n");
+    assert!(!ok);
+}
@@ -0,0 +1,105 @@
+#![allow(dead_code)]
+
+use std::fmt;
+
+use rand::Rng;
+use rstim::pauli::PauliString;
+use rstim::sim::tableau::StabilizerState;
+
+/// A gate of a random Clifford circuit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Gate {
+    H(usize),
+    S(usize),
+    SDag(usize),
+    X(usize),
+    Y(usize),
+    Z(usize),
+    Cx(usize, usize),
+    Cz(usize, usize),
+}
+
+impl Gate {
+    pub fn apply(self, st: &mut StabilizerState) {
+        match self {
+            Gate::H(a) => st.h(a),
+            Gate::S(a) => st.s(a),
+            Gate::SDag(a) => st.s_dag(a),
+            Gate::X(a) => st.x_gate(a),
+            Gate::Y(a) => st.y_gate(a),
+            Gate::Z(a) => st.z_gate(a),
+            Gate::Cx(a, b) => st.cx(a, b),
+            Gate::Cz(a, b) => st.cz(a, b),
+        }
+    }
+
+    /// Conjugates `p` by the gate, `P -> G P G†`.
+    pub fn conjugate(self, p: &mut PauliString) {
+        match self {
+            Gate::H(a) => p.h(a),
+            Gate::S(a) => p.s(a),
+            Gate::SDag(a) => p.s_dag(a),
+            Gate::X(a) => p.x_gate(a),
+            Gate::Y(a) => p.y_gate(a),
+            Gate::Z(a) => p.z_gate(a),
+            Gate::Cx(a, b) => p.cx(a, b),
+            Gate::Cz(a, b) => p.cz(a, b),
+        }
+    }
+}
+
+impl fmt::Display for Gate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Gate::H(a) => write!(f, "H {a}"),
+            Gate::S(a) => write!(f, "S {a}"),
+            Gate::SDag(a) => write!(f, "S_DAG {a}"),
+            Gate::X(a) => write!(f, "X {a}"),
+            Gate::Y(a) => write!(f, "Y {a}"),
+            Gate::Z(a) => write!(f, "Z {a}"),
+            Gate::Cx(a, b) => write!(f, "CX {a} {b}"),
+            Gate::Cz(a, b) => write!(f, "CZ {a} {b}"),
+        }
+    }
+}
+
+/// A uniformly chosen gate on qubits `0..n`, two-qubit gates only when
+/// `n > 1`.
+pub fn random_gate(rng: &mut impl Rng, n: usize) -> Gate {
+    let a = rng.gen_range(0..n);
+    let kinds = if n > 1 { 8 } else { 6 };
+    match rng.gen_range(0..kinds) {
+        0 => Gate::H(a),
+        1 => Gate::S(a),
+        2 => Gate::SDag(a),
+        3 => Gate::X(a),
+        4 => Gate::Y(a),
+        5 => Gate::Z(a),
+        k => {
+            let b = (a + rng.gen_range(1..n)) % n;
+            if k == 6 {
+                Gate::Cx(a, b)
+            } else {
+                Gate::Cz(a, b)
+            }
+        }
+    }
+}
+
+pub fn random_gates(rng: &mut impl Rng, n: usize, len: usize) -> Vec<Gate> {
+    (0..len).map(|_| random_gate(rng, n)).collect()
+}
+
+/// `len` random gates applied to `|0..0>` on `n` qubits.
+pub fn random_state(rng: &mut impl Rng, n: usize, len: usize) -> StabilizerState {
+    let mut st = StabilizerState::new(n);
+    for gate in random_gates(rng, n, len) {
+        gate.apply(&mut st);
+    }
+    st
+}
+
+/// Circuit text with one gate per line.
+pub fn circuit_text(gates: &[Gate]) -> String {
+    gates.iter().map(|g| format!("{g}\n")).collect()
+}
@@ -0,0 +1,108 @@
+mod clifford_helpers;
+use clifford_helpers::{circuit_text, random_gates};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rstim::circuit::Circuit;
+use rstim::clifford::Tableau;
+use rstim::pauli::PauliString;
+
+fn ps(s: &str) -> PauliString {
+    s.parse().unwrap()
+}
+
+fn tableau(text: &str, n: usize) -> Tableau {
+    Tableau::from_circuit(&Circuit::parse(text).unwrap(), n).unwrap()
+}
+
+#[test]
+fn gate_outputs() {
+    let h = Tableau::gate("H").unwrap();
+    assert_eq!(h.x_output(0).to_string(), "+Z");
+    assert_eq!(h.z_output(0).to_string(), "+X");
+    let cx = Tableau::gate("CNOT").unwrap();
+    assert_eq!(cx.x_output(0).to_string(), "+XX");
+    assert_eq!(cx.z_output(1).to_string(), "+ZZ");
+    assert_eq!(cx.apply(&ps("YZ")).to_string(), "+XY");
+    assert_eq!(
+        Tableau::gate("S").unwrap().apply(&ps("Y")).to_string(),
+        "-X"
+    );
+    assert!(Tableau::gate("M").is_err());
+}
+
+#[test]
+fn circuits_compare_up_to_global_phase() {
+    assert_eq!(tableau("H 0\nS 0\nS 0\nH 0", 1), tableau("X 0", 1));
+    assert_eq!(tableau("H 1\nCX 0 1\nH 1", 2), tableau("CZ 0 1", 2));
+    assert_eq!(
+        tableau("CX 0 1 1 0 0 1", 2),
+        tableau("REPEAT 3 {\n    CX 0 1 1 0 0 1\n}", 2)
+    );
+    assert_ne!(tableau("S 0", 1), tableau("S 0\nS 0\nS 0", 1));
+    assert_eq!(tableau("S 0\nS 0\nS 0", 1), tableau("S_DAG 0", 1));
+    assert!(Tableau::from_circuit(&Circuit::parse("H 0\nM 0").unwrap(), 1).is_err());
+}
+
+#[test]
+fn circuits_act_on_the_given_register() {
+    assert_eq!(tableau("H 2\nH 2", 3), tableau("", 3));
+    assert_eq!(tableau("", 3), Tableau::identity(3));
+    assert_ne!(tableau("H 2\nH 2", 3), tableau("", 2));
+    let err = Tableau::from_circuit(&Circuit::parse("H 2").unwrap(), 2).unwrap_err();
+    assert!(err.contains("3 qubits"), "{err}");
+}
+
+#[test]
+fn compose_invert_and_tensor() {
+    let s = Tableau::gate("S").unwrap();
+    let s_dag = Tableau::gate("S_DAG").unwrap();
+    assert_eq!(s.inverse(), s_dag);
+    assert_eq!(s.then(&s_dag), Tableau::identity(1));
+    let h_cx = tableau("H 0\nCX 0 1", 2);
+    assert_eq!(h_cx.then(&h_cx.inverse()), Tableau::identity(2));
+    assert_eq!(h_cx.inverse().then(&h_cx), Tableau::identity(2));
+
+    let h = Tableau::gate("H").unwrap();
+    let both = h.tensor(&Tableau::gate("CX").unwrap());
+    assert_eq!(both, tableau("H 0\nCX 1 2", 3));
+}
+
+#[test]
+fn append_and_prepend_on_targets() {
+    let mut t = tableau("H 0", 3);
+    t.append(&Tableau::gate("CX").unwrap(), &[0, 2]);
+    assert_eq!(t, tableau("H 0\nCX 0 2", 3));
+
+    let mut t = tableau("CX 1 0", 2);
+    t.prepend(&Tableau::gate("S").unwrap(), &[1]);
+    t.prepend(&Tableau::gate("CZ").unwrap(), &[1, 0]);
+    assert_eq!(t, tableau("CZ 1 0\nS 1\nCX 1 0", 2));
+}
+
+#[test]
+fn synthesized_circuits_round_trip() {
+    let mut rng = StdRng::seed_from_u64(44);
+    for _ in 0..100 {
+        let n = rng.gen_range(2..6);
+        let t = tableau(&circuit_text(&random_gates(&mut rng, n, 40)), n);
+        let synthesized = t.to_circuit();
+        for instr in &synthesized.instrs {
+            let rstim::ir::StimInstr::Op { name, .. } = instr else {
+                panic!("unexpected block");
+            };
+            assert!(["H", "S_DAG", "CX", "X", "Z"].contains(&name.as_str()));
+        }
+        let back = Tableau::from_circuit(&synthesized, t.num_qubits()).unwrap();
+        assert_eq!(back, t);
+        assert_eq!(t.then(&t.inverse()), Tableau::identity(t.num_qubits()));
+    }
+}
+
+#[test]
+fn from_outputs_checks_commutation() {
+    let swap = Tableau::from_outputs(vec![ps("_X"), ps("X_")], vec![ps("_Z"), ps("Z_")]).unwrap();
+    assert_eq!(swap, tableau("CX 0 1 1 0 0 1", 2));
+    assert!(Tableau::from_outputs(vec![ps("X")], vec![ps("X")]).is_err());
+    assert!(Tableau::from_outputs(vec![ps("iX")], vec![ps("Z")]).is_err());
+    assert!(Tableau::from_outputs(vec![ps("X_"), ps("Z_")], vec![ps("_Z"), ps("_X")]).is_err());
+}
@@ -0,0 +1,173 @@
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use rstim::compiled::{Basis, CompiledCircuit, Op};
+use rstim::executor::Executor;
+use rstim::ir::{StimInstr, StimTarget};
+use rstim::parser::parse_lines;
+use rstim::sim::frame::FrameSimulator;
+
+fn compile(program: &str) -> Result<CompiledCircuit, String> {
+    CompiledCircuit::compile(&parse_lines(program).unwrap())
+}
+
+#[test]
+fn lowers_repeat_to_loop_markers() {
+    let c = compile("H 0\nREPEAT 3 {\nM 0\n}\n").unwrap();
+    assert_eq!(c.ops().len(), 4);
+    assert_eq!(c.ops()[1], Op::Repeat { count: 3, end: 3 });
+    assert_eq!(c.ops()[3], Op::EndRepeat { start: 1 });
+    assert_eq!(c.num_measurements(), 3);
+}
+
+#[test]
+fn rejects_bad_programs_at_compile_time() {
+    assert!(compile("CX 0\n").is_err());
+    assert!(compile("M 0\nDETECTOR rec[-2]\n").is_err());
+    assert!(compile("REPEAT 2 {\nDETECTOR rec[-1]\nM 0\n}\n").is_err());
+    assert!(compile("FROB 0\n").is_err());
+    assert!(compile("CX 0 0\n").is_err());
+    assert!(compile("CZ 1 1\n").is_err());
+    assert!(compile("DEPOLARIZE2(0.1) 0 1 2 2\n").is_err());
+    assert!(compile("X_ERROR(1.5) 0\n").is_err());
+    assert!(compile("DEPOLARIZE1(-0.1) 0\n").is_err());
+    assert!(compile("M 0\nOBSERVABLE_INCLUDE(-1) rec[-1]\n").is_err());
+    assert!(compile("M 0\nOBSERVABLE_INCLUDE(0.5) rec[-1]\n").is_err());
+    assert!(compile("M 0\nOBSERVABLE_INCLUDE(4294967296) rec[-1]\n").is_err());
+    assert!(compile("M 0\nOBSERVABLE_INCLUDE(4294967295) rec[-1]\n").is_ok());
+    assert!(compile("M 0\nREPEAT 2 {\nM 0\nDETECTOR rec[-2]\n}\n").is_ok());
+}
+
+#[test]
+fn rejects_non_negative_recs_built_directly() {
+    for o in [0, 1] {
+        let instrs = vec![
+            StimInstr::Op {
+                name: "M".to_string(),
+                tag: None,
+                args: vec![],
+                targets: vec![StimTarget::Qubit(0)],
+            },
+            StimInstr::Op {
+                name: "DETECTOR".to_string(),
+                tag: None,
+                args: vec![],
+                targets: vec![StimTarget::Rec(o)],
+            },
+        ];
+        assert!(CompiledCircuit::compile(&instrs).is_err());
+    }
+}
+
+#[test]
+fn repeat_iterations_share_state_and_record() {
+    let program = "M 0\nREPEAT 2 {\nX 0\nM 0\nDETECTOR rec[-1] rec[-2]\n}\n";
+    let mut ex = Executor::from_instrs(parse_lines(program).unwrap()).unwrap();
+    let out = ex.run(&mut StdRng::seed_from_u64(1)).unwrap();
+    assert_eq!(out.measurements, vec![false, true, false]);
+    assert_eq!(out.detectors, vec![true, true]);
+}
+
+#[test]
+fn frame_simulator_runs_compiled_circuits() {
+    let c = compile(
+        "H 0\nCX 0 1\nX_ERROR(1) 1\nM 0 1\nDETECTOR rec[-1] rec[-2]\nOBSERVABLE_INCLUDE(1) rec[-2]\n",
+    )
+    .unwrap();
+    let lanes = 100;
+    let mut sim = FrameSimulator::new(c.num_qubits(), lanes);
+    let record = sim.run(&c, &mut StdRng::seed_from_u64(5)).unwrap();
+    assert_eq!(record.detectors.len(), 1);
+    let ones = |w: &[u64]| w.iter().map(|x| x.count_ones()).sum::<u32>();
+    assert_eq!(ones(&record.detectors[0]), lanes as u32);
+    // The Bell pair's outcome is random relative to any reference.
+    let flips = ones(&record.observables[1]);
+    assert!(flips > 20 && flips < 80, "{flips}");
+}
+
+#[test]
+fn frame_noise_matches_probability() {
+    let c = compile("X_ERROR(0.1) 0\nM 0\nDETECTOR rec[-1]\n").unwrap();
+    let lanes = 50_000;
+    let mut sim = FrameSimulator::new(1, lanes);
+    let record = sim.run(&c, &mut StdRng::seed_from_u64(9)).unwrap();
+    let hits: u32 = record.detectors[0].iter().map(|w| w.count_ones()).sum();
+    let sigma = (lanes as f64 * 0.1 * 0.9).sqrt();
+    assert!((hits as f64 - 5000.0).abs() < 5.0 * sigma, "{hits}");
+}
+
+#[test]
+fn resets_clear_state_and_errors() {
+    let c = compile("R 0\nRX 1\nRY 2\n").unwrap();
+    assert_eq!(
+        c.ops()[1],
+        Op::Reset {
+            basis: Basis::X,
+            targets: vec![1]
+        }
+    );
+
+    let program = "X 0\nH 1\nR 0 1\nM 0 1\nRX 0\nMX 0\nRY 0\nMY 0\n";
+    let mut ex = Executor::from_instrs(parse_lines(program).unwrap()).unwrap();
+    for seed in 0..10 {
+        let out = ex.run(&mut StdRng::seed_from_u64(seed)).unwrap();
+        assert_eq!(out.measurements, vec![false; 4]);
+    }
+
+    let c =
+        compile("X_ERROR(1) 0\nR 0\nM 0\nDETECTOR rec[-1]\nR 0\nMX 0\nDETECTOR rec[-1]\n").unwrap();
+    let lanes = 256;
+    let mut sim = FrameSimulator::new(c.num_qubits(), lanes);
+    let record = sim.run(&c, &mut StdRng::seed_from_u64(3)).unwrap();
+    let ones = |w: &[u64]| w.iter().map(|x| x.count_ones()).sum::<u32>();
+    assert_eq!(ones(&record.detectors[0]), 0);
+    let flips = ones(&record.detectors[1]);
+    assert!(flips > 80 && flips < 176, "{flips}");
+}
+
+#[test]
+fn frame_detectors_reach_back_to_their_oldest_rec() {
+    let c = compile("X_ERROR(1) 0\nM 0 1 2\nDETECTOR rec[-3]\nM 1\nDETECTOR rec[-2] rec[-4]\n")
+        .unwrap();
+    assert_eq!(c.max_lookback(), 4);
+    let lanes = 70;
+    let mut sim = FrameSimulator::new(c.num_qubits(), lanes);
+    let record = sim.run(&c, &mut StdRng::seed_from_u64(4)).unwrap();
+    let ones = |w: &[u64]| w.iter().map(|x| x.count_ones()).sum::<u32>();
+    assert_eq!(ones(&record.detectors[0]), lanes as u32);
+    assert_eq!(ones(&record.detectors[1]), lanes as u32);
+}
+
+#[test]
+fn frame_simulator_keeps_measurements_on_request() {
+    let c = compile("X_ERROR(1) 0\nM 0 1\nM 0\n").unwrap();
+    let mut sim = FrameSimulator::new(c.num_qubits(), 10);
+    let record = sim.run(&c, &mut StdRng::seed_from_u64(6)).unwrap();
+    assert!(record.measurements.is_empty());
+    sim.keep_measurements(true);
+    let record = sim.run(&c, &mut StdRng::seed_from_u64(6)).unwrap();
+    assert_eq!(record.measurements, vec![vec![0x3ff], vec![0], vec![0x3ff]]);
+}
+
+#[test]
+fn records_the_width_each_op_needs() {
+    let c = compile("H 1\nCX sweep[0] 2\nTICK\nM 0 !4\nDEPOLARIZE2(0.1) 3 0").unwrap();
+    let widths: Vec<usize> = (0..c.ops().len()).map(|pc| c.width(pc)).collect();
+    assert_eq!(widths, vec![2, 3, 0, 5, 4]);
+    assert_eq!(c.num_qubits(), 5);
+}
+
+#[test]
+fn streamed_instructions_compile_like_a_slice() {
+    let program = "H 0\nM 0\nREPEAT 3 {\nCX 0 1\nM 1\nDETECTOR rec[-1] rec[-2]\n}\nM 2\n";
+    let instrs = parse_lines(program).unwrap();
+    let streamed = CompiledCircuit::from_stream(instrs.iter().cloned().map(Ok)).unwrap();
+    assert_eq!(streamed, CompiledCircuit::compile(&instrs).unwrap());
+    assert_eq!(streamed.num_measurements(), 5);
+    assert_eq!(streamed.num_detectors(), 3);
+    assert_eq!(streamed.num_qubits(), 3);
+    let failing = [Ok(instrs[0].clone()), Err("bad line".to_string())];
+    assert_eq!(
+        CompiledCircuit::from_stream(failing),
+        Err("bad line".to_string())
+    );
+}
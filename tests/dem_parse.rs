@@ -0,0 +1,42 @@
+use rstim::dem::{DemInstr, DemTarget, DetectorErrorModel};
+
+#[test]
+fn round_trips_through_display() {
+    let text = "error(0.125) D0 D1 ^ L0\ndetector(1, 0) D0\nlogical_observable L1\nrepeat 3 {\n    error(0.25) D1\n    shift_detectors(0, 1) 1\n}\n";
+    let dem = DetectorErrorModel::parse(text).unwrap();
+    assert_eq!(dem.to_string(), text);
+    assert_eq!(dem.num_detectors(), 4);
+    assert_eq!(dem.num_observables(), 2);
+}
+
+#[test]
+fn accepts_comments_and_brace_on_next_line() {
+    let dem: DetectorErrorModel = "# header\nREPEAT 2\n{\nerror(0.5) D0 # trailing\n}\n"
+        .parse()
+        .unwrap();
+    assert_eq!(
+        dem.instrs,
+        vec![DemInstr::Repeat {
+            count: 2,
+            body: vec![DemInstr::Error {
+                probability: 0.5,
+                targets: vec![DemTarget::Detector(0)],
+            }],
+        }]
+    );
+}
+
+#[test]
+fn rejects_malformed_models() {
+    for bad in [
+        "error(1.5) D0",
+        "error D0",
+        "error(0.1) X0",
+        "detector L0",
+        "repeat 2 {\nerror(0.1) D0\n",
+        "}",
+        "frobnicate D0",
+    ] {
+        assert!(DetectorErrorModel::parse(bad).is_err(), "{bad}");
+    }
+}
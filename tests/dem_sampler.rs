@@ -0,0 +1,50 @@
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use rstim::dem::DetectorErrorModel;
+use rstim::dem_sampler::DemSampler;
+
+#[test]
+fn certain_errors_always_fire() {
+    let dem = DetectorErrorModel::parse("error(1) D0 L0\nerror(0) D1\nerror(1) D2 D2\n").unwrap();
+    let sampler = DemSampler::new(&dem);
+    let mut rng = StdRng::seed_from_u64(1);
+    for shot in sampler.sample_shots(10, &mut rng) {
+        assert_eq!(shot.detectors, vec![true, false, false]);
+        assert_eq!(shot.observables, vec![true]);
+    }
+}
+
+#[test]
+fn repeat_blocks_shift_detectors() {
+    let dem = DetectorErrorModel::parse("repeat 3 {\nerror(1) D0\nshift_detectors 1\n}\n").unwrap();
+    let sampler = DemSampler::new(&dem);
+    let shot = sampler.sample(&mut StdRng::seed_from_u64(2));
+    assert_eq!(shot.detectors, vec![true, true, true]);
+}
+
+#[test]
+fn firing_rate_matches_probability() {
+    let mut rng = StdRng::seed_from_u64(3);
+    for p in [0.01, 0.3] {
+        let dem = DetectorErrorModel::parse(&format!("error({p}) D0")).unwrap();
+        let shots = 20000;
+        let hits = DemSampler::new(&dem)
+            .sample_shots(shots, &mut rng)
+            .iter()
+            .filter(|s| s.detectors[0])
+            .count();
+        let rate = hits as f64 / shots as f64;
+        let sigma = (p * (1.0 - p) / shots as f64).sqrt();
+        assert!((rate - p).abs() < 5.0 * sigma, "p={p} rate={rate}");
+    }
+}
+
+#[test]
+fn shots_do_not_depend_on_the_range() {
+    let dem = DetectorErrorModel::parse("error(0.2) D0\nerror(0.4) D1 L0\n").unwrap();
+    let sampler = DemSampler::new(&dem);
+    let all = sampler.sample_range(11, 0, 600);
+    assert_eq!(all.len(), 600);
+    assert_eq!(sampler.sample_range(11, 250, 300), all[250..550]);
+    assert_ne!(sampler.sample_range(12, 0, 600), all);
+}
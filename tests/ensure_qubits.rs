@@ -0,0 +1,66 @@
+mod clifford_helpers;
+use clifford_helpers::random_gate;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rstim::executor::Executor;
+use rstim::parser::parse_lines;
+use rstim::pauli::PauliString;
+use rstim::seed::ShotRng;
+use rstim::sim::tableau::StabilizerState;
+
+fn ps(s: &str) -> PauliString {
+    s.parse().unwrap()
+}
+
+#[test]
+fn growing_keeps_existing_state() {
+    let mut state = StabilizerState::new(2);
+    state.h(0);
+    state.cx(0, 1);
+    state.x_gate(1);
+    state.ensure_qubits(4);
+    assert_eq!(state.num_qubits(), 4);
+    assert_eq!(state.peek_observable_expectation(&ps("XX__")).unwrap(), 1);
+    assert_eq!(state.peek_observable_expectation(&ps("ZZ__")).unwrap(), -1);
+    assert_eq!(state.peek_z(2), 1);
+    assert_eq!(state.peek_z(3), 1);
+
+    state.ensure_qubits(3);
+    assert_eq!(state.num_qubits(), 4);
+}
+
+#[test]
+fn growing_mid_circuit_matches_a_full_size_state() {
+    let mut rng = StdRng::seed_from_u64(48);
+    for _ in 0..100 {
+        let n = rng.gen_range(2..7);
+        let k = rng.gen_range(1..n);
+        let mut grown = StabilizerState::new(k);
+        let mut full = StabilizerState::new(n);
+        for step in 0..40 {
+            // The first half only touches the first k qubits.
+            let width = if step < 20 { k } else { n };
+            if step == 20 {
+                grown.ensure_qubits(n);
+            }
+            let gate = random_gate(&mut rng, width);
+            gate.apply(&mut grown);
+            gate.apply(&mut full);
+        }
+        assert_eq!(grown.canonical_stabilizers(), full.canonical_stabilizers());
+    }
+}
+
+#[test]
+fn executor_grows_a_state_fed_piece_by_piece() {
+    let mut first = Executor::from_instrs(parse_lines("H 0").unwrap()).unwrap();
+    let mut second = Executor::from_instrs(parse_lines("CX 0 3\nM 0 3").unwrap()).unwrap();
+    for shot in 0..20 {
+        let mut rng = ShotRng::new(5, shot);
+        let mut state = StabilizerState::new(0);
+        first.run_on(&mut state, &mut rng).unwrap();
+        let out = second.run_on(&mut state, &mut rng).unwrap();
+        assert_eq!(state.num_qubits(), 4);
+        assert_eq!(out.measurements[0], out.measurements[1]);
+    }
+}
@@ -0,0 +1,143 @@
+mod clifford_helpers;
+use clifford_helpers::random_state;
+use num_complex::Complex64;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use rstim::sim::tableau::StabilizerState;
+
+fn bell_and_one() -> StabilizerState {
+    let mut s = StabilizerState::new(3);
+    s.h(0);
+    s.cx(0, 1);
+    s.x_gate(2);
+    s
+}
+
+/// log2 of the Schmidt rank of `amps` across `subset`, by dense elimination.
+fn schmidt_entropy(amps: &[Complex64], n: usize, subset: &[usize]) -> usize {
+    let rest: Vec<usize> = (0..n).filter(|q| !subset.contains(q)).collect();
+    let index = |qs: &[usize], j: usize| {
+        qs.iter()
+            .enumerate()
+            .map(|(k, &q)| (j >> q & 1) << k)
+            .sum::<usize>()
+    };
+    let mut m = vec![vec![Complex64::new(0.0, 0.0); 1 << rest.len()]; 1 << subset.len()];
+    for (j, a) in amps.iter().enumerate() {
+        m[index(subset, j)][index(&rest, j)] = *a;
+    }
+    let mut rank = 0;
+    for col in 0..1 << rest.len() {
+        let Some(p) = (rank..m.len()).find(|&r| m[r][col].norm() > 1e-9) else {
+            continue;
+        };
+        m.swap(rank, p);
+        for r in 0..m.len() {
+            if r != rank {
+                let f = m[r][col] / m[rank][col];
+                let pivot = m[rank].clone();
+                for (x, y) in m[r].iter_mut().zip(&pivot) {
+                    *x -= f * y;
+                }
+            }
+        }
+        rank += 1;
+    }
+    rank.trailing_zeros() as usize
+}
+
+#[test]
+fn bell_pair_entropies() {
+    let s = bell_and_one();
+    assert_eq!(s.entanglement_entropy(&[0]).unwrap(), 1);
+    assert_eq!(s.entanglement_entropy(&[1, 2]).unwrap(), 1);
+    assert_eq!(s.entanglement_entropy(&[0, 1]).unwrap(), 0);
+    assert_eq!(s.entanglement_entropy(&[2]).unwrap(), 0);
+    assert_eq!(s.entanglement_entropy(&[]).unwrap(), 0);
+    assert_eq!(s.mutual_information(&[0], &[1]).unwrap(), 2);
+    assert_eq!(s.mutual_information(&[0], &[2]).unwrap(), 0);
+}
+
+#[test]
+fn reduced_stabilizers_follow_the_given_order() {
+    let s = bell_and_one();
+    let strings = |qs: &[usize]| -> Vec<String> {
+        s.reduced_stabilizers(qs)
+            .unwrap()
+            .iter()
+            .map(|p| p.to_string())
+            .collect()
+    };
+    assert_eq!(strings(&[2]), ["-Z"]);
+    assert!(strings(&[0]).is_empty());
+    assert_eq!(strings(&[1, 0]), ["+XX", "+ZZ"]);
+    assert_eq!(strings(&[2, 0, 1]), ["-Z__", "+_XX", "+_ZZ"]);
+}
+
+#[test]
+fn ghz_mutual_information() {
+    let mut s = StabilizerState::new(4);
+    s.h(0);
+    for q in 1..4 {
+        s.cx(0, q);
+    }
+    for subset in [&[0][..], &[1, 2], &[0, 3, 2]] {
+        assert_eq!(s.entanglement_entropy(subset).unwrap(), 1);
+    }
+    assert_eq!(s.mutual_information(&[0], &[1]).unwrap(), 1);
+    assert_eq!(s.mutual_information(&[0, 1], &[2, 3]).unwrap(), 2);
+}
+
+#[test]
+fn random_states_match_schmidt_rank() {
+    let mut rng = StdRng::seed_from_u64(49);
+    for _ in 0..100 {
+        let n = rng.gen_range(1..6);
+        let s = random_state(&mut rng, n, 20);
+        let mut qubits: Vec<usize> = (0..n).collect();
+        qubits.shuffle(&mut rng);
+        let subset = &qubits[..rng.gen_range(0..=n)];
+        let amps = s.to_state_vector().unwrap();
+        assert_eq!(
+            s.entanglement_entropy(subset).unwrap(),
+            schmidt_entropy(&amps, n, subset)
+        );
+        for p in s.reduced_stabilizers(subset).unwrap() {
+            let mut full = rstim::pauli::PauliString::new(n);
+            for (j, &q) in subset.iter().enumerate() {
+                full.set(q, p.get(j));
+            }
+            full.set_phase(p.phase());
+            assert_eq!(s.peek_observable_expectation(&full).unwrap(), 1);
+        }
+    }
+}
+
+#[test]
+fn large_chain_of_bell_pairs() {
+    let n = 400;
+    let mut s = StabilizerState::new(n);
+    for i in (0..n).step_by(2) {
+        s.h(i);
+        s.cx(i, i + 1);
+    }
+    let left: Vec<usize> = (0..201).collect();
+    assert_eq!(s.entanglement_entropy(&left).unwrap(), 1);
+    let evens: Vec<usize> = (0..n).step_by(2).collect();
+    assert_eq!(s.entanglement_entropy(&evens).unwrap(), n / 2);
+}
+
+#[test]
+fn bad_qubit_sets_are_rejected() {
+    let s = bell_and_one();
+    assert_eq!(
+        s.mutual_information(&[0, 1], &[1]),
+        Err("qubit 1 listed twice".to_string())
+    );
+    assert_eq!(
+        s.entanglement_entropy(&[0, 3]),
+        Err("qubit 3 out of range".to_string())
+    );
+    assert!(s.reduced_stabilizers(&[2, 2]).is_err());
+}
@@ -0,0 +1,161 @@
+use rstim::dem::{DemInstr, DemTarget};
+use rstim::error_analyzer::{ErrorAnalyzerOptions, analyze_errors};
+use rstim::parser::parse_lines;
+
+fn dem(program: &str, opts: &ErrorAnalyzerOptions) -> String {
+    analyze_errors(&parse_lines(program).unwrap(), opts)
+        .unwrap()
+        .to_string()
+}
+
+#[test]
+fn single_x_error() {
+    let program = "X_ERROR(0.125) 0\nM 0\nDETECTOR rec[-1]\nOBSERVABLE_INCLUDE(0) rec[-1]\n";
+    assert_eq!(dem(program, &Default::default()), "error(0.125) D0 L0\n");
+}
+
+#[test]
+fn z_error_is_invisible_to_z_measurement() {
+    let program = "Z_ERROR(0.1) 0\nM 0\nDETECTOR rec[-1]\n";
+    assert_eq!(dem(program, &Default::default()), "detector D0\n");
+}
+
+#[test]
+fn merges_depolarizing_cases_with_equal_symptoms() {
+    let program = "DEPOLARIZE1(0.3) 0\nM 0\nDETECTOR rec[-1]\n";
+    let model = analyze_errors(&parse_lines(program).unwrap(), &Default::default()).unwrap();
+    match &model.instrs[..] {
+        [
+            DemInstr::Error {
+                probability,
+                targets,
+            },
+        ] => {
+            assert!((probability - 0.2).abs() < 1e-12);
+            assert_eq!(targets, &vec![DemTarget::Detector(0)]);
+        }
+        other => panic!("unexpected model {other:?}"),
+    }
+}
+
+#[test]
+fn propagates_through_cx() {
+    let program = "X_ERROR(0.25) 0\nCX 0 1\nM 0 1\nDETECTOR rec[-1]\nDETECTOR rec[-2]\n";
+    assert_eq!(dem(program, &Default::default()), "error(0.25) D0 D1\n");
+}
+
+#[test]
+fn decomposes_into_graphlike_components() {
+    let program = "DEPOLARIZE1(0.1) 0\nCX 0 1\nM 0 1\nMX 0 0\n\
+                   DETECTOR rec[-4]\nDETECTOR rec[-3]\nDETECTOR rec[-2]\nDETECTOR rec[-1]\n";
+    let instrs = parse_lines(program).unwrap();
+    let plain = analyze_errors(&instrs, &Default::default()).unwrap();
+    assert!(plain.to_string().contains(" D0 D1 D2 D3\n"));
+
+    let opts = ErrorAnalyzerOptions {
+        decompose_errors: true,
+        ..Default::default()
+    };
+    let model = analyze_errors(&instrs, &opts).unwrap().to_string();
+    let lines: Vec<&str> = model
+        .lines()
+        .map(|l| l.split(") ").nth(1).unwrap())
+        .collect();
+    assert_eq!(lines, vec!["D0 D1", "D0 D1 ^ D2 D3", "D2 D3"]);
+}
+
+#[test]
+fn reports_decomposition_failures() {
+    let program = "X_ERROR(0.1) 0\nCX 0 1 0 2\nM 0 1 2\n\
+                   DETECTOR rec[-1]\nDETECTOR rec[-2]\nDETECTOR rec[-3]\n";
+    let instrs = parse_lines(program).unwrap();
+    let mut opts = ErrorAnalyzerOptions {
+        decompose_errors: true,
+        ..Default::default()
+    };
+    assert!(analyze_errors(&instrs, &opts).is_err());
+    opts.ignore_decomposition_failures = true;
+    assert_eq!(
+        analyze_errors(&instrs, &opts).unwrap().to_string(),
+        "error(0.1) D0 D1 D2\n"
+    );
+}
+
+const LOOP: &str = "M 0\nREPEAT 5 {\nX_ERROR(0.1) 0\nM 0\nDETECTOR(1, 0) rec[-1] rec[-2]\nSHIFT_COORDS(0, 1)\n}\nOBSERVABLE_INCLUDE(0) rec[-1]\n";
+
+#[test]
+fn unrolls_loops_by_default() {
+    let model = dem(LOOP, &Default::default());
+    assert!(model.contains("error(0.1) D4 L0\n"));
+    assert!(model.contains("detector(1, 4) D4\n"));
+    assert!(!model.contains("repeat"));
+}
+
+#[test]
+fn folds_periodic_loops() {
+    let opts = ErrorAnalyzerOptions {
+        fold_loops: true,
+        ..Default::default()
+    };
+    let instrs = parse_lines(LOOP).unwrap();
+    let model = analyze_errors(&instrs, &opts).unwrap();
+    assert_eq!(
+        model.to_string(),
+        "repeat 5 {\n    error(0.1) D0 L0\n    detector(1, 0) D0\n    shift_detectors(0, 1) 1\n}\n"
+    );
+    assert_eq!(model.num_detectors(), 5);
+    assert_eq!(model.num_observables(), 1);
+}
+
+#[test]
+fn folds_long_loops_without_unrolling() {
+    let program = "R 0 1 2\nCX 0 1 2 1\nM 1\nDETECTOR rec[-1]\nR 1\n\
+                   REPEAT 100000 {\nDEPOLARIZE1(0.01) 0 2\nCX 0 1 2 1\nM 1\nR 1\nDETECTOR rec[-1] rec[-2]\n}\n\
+                   M 0 2\nDETECTOR rec[-1] rec[-2] rec[-3]\nOBSERVABLE_INCLUDE(0) rec[-1]\n";
+    let opts = ErrorAnalyzerOptions {
+        fold_loops: true,
+        ..Default::default()
+    };
+    let model = analyze_errors(&parse_lines(program).unwrap(), &opts).unwrap();
+    match &model.instrs[..] {
+        [
+            DemInstr::Repeat { count, body },
+            DemInstr::Detector { index: 1, .. },
+        ] => {
+            assert_eq!(*count, 100_000);
+            let targets: Vec<&Vec<DemTarget>> = body
+                .iter()
+                .filter_map(|i| match i {
+                    DemInstr::Error { targets, .. } => Some(targets),
+                    _ => None,
+                })
+                .collect();
+            assert_eq!(
+                targets,
+                vec![
+                    &vec![DemTarget::Detector(1)],
+                    &vec![DemTarget::Detector(1), DemTarget::Observable(0)],
+                ]
+            );
+        }
+        other => panic!("unexpected model {other:?}"),
+    }
+    assert_eq!(model.num_detectors(), 100_002);
+}
+
+#[test]
+fn folds_nested_loops() {
+    let program = "M 0\nREPEAT 3 {\nREPEAT 4 {\nX_ERROR(0.1) 0\nM 0\nDETECTOR(1, 0) rec[-1] rec[-2]\nSHIFT_COORDS(0, 1)\n}\nSHIFT_COORDS(1)\n}\nOBSERVABLE_INCLUDE(0) rec[-1]\n";
+    let opts = ErrorAnalyzerOptions {
+        fold_loops: true,
+        ..Default::default()
+    };
+    let model = analyze_errors(&parse_lines(program).unwrap(), &opts).unwrap();
+    assert_eq!(
+        model.to_string(),
+        "repeat 3 {\n    repeat 4 {\n        error(0.1) D0 L0\n        detector(1, 0) D0\n        shift_detectors(0, 1) 1\n    }\n    shift_detectors(1, 0) 0\n}\n"
+    );
+    assert_eq!(model.num_detectors(), 12);
+    let unrolled = dem(program, &Default::default());
+    assert!(unrolled.contains("detector(3, 11) D11\n"));
+}
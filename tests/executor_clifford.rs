@@ -11,3 +11,15 @@ fn bell_pair_measurements_match() {
     let out = ex.run(&mut rng).unwrap();
     assert_eq!(out.measurements[0], out.measurements[1]);
 }
+
+#[test]
+fn s_dag_undoes_s() {
+    let program = "H 0 1\nS 0\nS_DAG 0\nS_DAG 1\nS_DAG 1\nH 0 1\nM 0 1\n";
+    let instrs = parse_lines(program).unwrap();
+    let mut ex = Executor::from_instrs(instrs).unwrap();
+    let mut rng = StdRng::seed_from_u64(1);
+    for _ in 0..10 {
+        let out = ex.run(&mut rng).unwrap();
+        assert_eq!(out.measurements, vec![false, true]);
+    }
+}
@@ -0,0 +1,75 @@
+use rstim::m2d::M2dConverter;
+use rstim::parser::parse_lines;
+
+#[test]
+fn converts_measurements_relative_to_reference() {
+    let instrs = parse_lines(
+        "X 1\nM 0 1\nDETECTOR rec[-2]\nDETECTOR rec[-1]\nOBSERVABLE_INCLUDE(0) rec[-1]\n",
+    )
+    .unwrap();
+    let mut conv = M2dConverter::new(&instrs).unwrap();
+    assert_eq!(conv.num_measurements(), 2);
+    let (dets, obs) = conv.convert(&[false, true], &[]).unwrap();
+    assert_eq!(dets, vec![false, false]);
+    assert_eq!(obs, vec![false]);
+    let (dets, obs) = conv.convert(&[true, false], &[]).unwrap();
+    assert_eq!(dets, vec![true, true]);
+    assert_eq!(obs, vec![true]);
+}
+
+#[test]
+fn sweep_bits_change_the_reference() {
+    let instrs = parse_lines("CX sweep[0] 0\nM 0\nDETECTOR rec[-1]\n").unwrap();
+    let mut conv = M2dConverter::new(&instrs).unwrap();
+    assert_eq!(conv.num_sweep_bits(), 1);
+    assert_eq!(conv.convert(&[true], &[true]).unwrap().0, vec![false]);
+    assert_eq!(conv.convert(&[true], &[false]).unwrap().0, vec![true]);
+}
+
+#[test]
+fn rejects_wrong_measurement_count() {
+    let instrs = parse_lines("M 0\nDETECTOR rec[-1]\n").unwrap();
+    let mut conv = M2dConverter::new(&instrs).unwrap();
+    assert!(conv.convert(&[false, false], &[]).is_err());
+}
+
+#[test]
+fn rejects_sweep_bits_past_the_last_sweep_target() {
+    let instrs = parse_lines("CX sweep[0] 0\nM 0\nDETECTOR rec[-1]\n").unwrap();
+    let mut conv = M2dConverter::new(&instrs).unwrap();
+    assert!(conv.convert(&[false], &[false, true]).is_err());
+}
+
+#[test]
+fn only_read_sweep_bits_select_the_reference() {
+    let instrs = parse_lines("CX sweep[2] 0\nM 0\nDETECTOR rec[-1]\n").unwrap();
+    let mut conv = M2dConverter::new(&instrs).unwrap();
+    assert_eq!(conv.num_sweep_bits(), 3);
+    assert_eq!(
+        conv.convert(&[true], &[true, true, false]).unwrap().0,
+        vec![true]
+    );
+    assert_eq!(
+        conv.convert(&[true], &[false, true, true]).unwrap().0,
+        vec![false]
+    );
+    assert_eq!(
+        conv.convert(&[true], &[true, false, true]).unwrap().0,
+        vec![false]
+    );
+}
+
+#[test]
+fn walks_repeat_blocks() {
+    let instrs = parse_lines(
+        "M 0\nREPEAT 3 {\nX 0\nM 0\nDETECTOR rec[-1] rec[-2]\n}\nOBSERVABLE_INCLUDE(0) rec[-1]\n",
+    )
+    .unwrap();
+    let mut conv = M2dConverter::new(&instrs).unwrap();
+    let (dets, obs) = conv.convert(&[false, true, false, true], &[]).unwrap();
+    assert_eq!(dets, vec![false, false, false]);
+    assert_eq!(obs, vec![false]);
+    let (dets, obs) = conv.convert(&[false, true, true, true], &[]).unwrap();
+    assert_eq!(dets, vec![false, true, true]);
+    assert_eq!(obs, vec![false]);
+}
@@ -0,0 +1,53 @@
+use rstim::circuit::Circuit;
+use rstim::normalize::normalize;
+use rstim::parser::parse_lines;
+
+fn normalized(program: &str) -> Circuit {
+    Circuit::from_instrs(normalize(parse_lines(program).unwrap()))
+}
+
+#[test]
+fn fuses_adjacent_gates() {
+    let out = normalized("H 0\nH 1\nH 2\nCX 0 1\nCX 2 3\n");
+    assert_eq!(out, Circuit::parse("H 0 1 2\nCX 0 1 2 3\n").unwrap());
+}
+
+#[test]
+fn does_not_fuse_overlapping_targets() {
+    let out = normalized("CX 0 1\nCX 1 2\n");
+    assert_eq!(out.len(), 2);
+}
+
+#[test]
+fn respects_args_and_tags() {
+    let out = normalized("X_ERROR(0.1) 0\nX_ERROR(0.2) 1\n");
+    assert_eq!(out.len(), 2);
+}
+
+#[test]
+fn fuses_across_disjoint_gates_within_tick_region() {
+    let out = normalized("H 0\nX 1\nH 2\nTICK\nH 3\n");
+    assert_eq!(out, Circuit::parse("H 0 2\nX 1\nTICK\nH 3\n").unwrap());
+}
+
+#[test]
+fn keeps_measurement_and_detector_order() {
+    let program = "M 0\nDETECTOR rec[-1]\nM 1\nDETECTOR rec[-1]\n";
+    assert_eq!(normalized(program), Circuit::parse(program).unwrap());
+}
+
+#[test]
+fn drops_empty_instructions_and_repeats() {
+    let out = normalized("H\nREPEAT 3 {\nX\n}\nTICK\n");
+    assert_eq!(out, Circuit::parse("TICK\n").unwrap());
+}
+
+#[test]
+fn fuses_long_layers() {
+    let mut program = String::new();
+    for q in 0..20_000 {
+        program.push_str(&format!("H {q}\nX_ERROR(0.1) {q}\n"));
+    }
+    let out = normalized(&program);
+    assert_eq!(out.len(), 2);
+}
@@ -0,0 +1,13 @@
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use rstim::{executor::Executor, parser::parse_lines};
+
+#[test]
+fn xors_includes_per_observable() {
+    let program = "X 0\nM 0 1\nOBSERVABLE_INCLUDE(1) rec[-2]\nOBSERVABLE_INCLUDE(1) rec[-2]\nOBSERVABLE_INCLUDE(0) rec[-2] rec[-1]\n";
+    let instrs = parse_lines(program).unwrap();
+    let mut ex = Executor::from_instrs(instrs).unwrap();
+    let mut rng = StdRng::seed_from_u64(1);
+    let out = ex.run(&mut rng).unwrap();
+    assert_eq!(out.observable_flips(3), vec![true, false, false]);
+}
@@ -0,0 +1,75 @@
+use rstim::ir::{StimInstr, StimTarget};
+use rstim::parser::parse_lines;
+
+#[test]
+fn allows_spaces_inside_parens() {
+    let instrs = parse_lines("PAULI_CHANNEL_1( 0.1 , 0.2,0.3 ) 0\n").unwrap();
+    assert_eq!(instrs[0].args().unwrap(), &[0.1, 0.2, 0.3]);
+}
+
+#[test]
+fn accepts_scientific_notation() {
+    let instrs = parse_lines("X_ERROR(1e-3) 0\nZ_ERROR(2.5E-2) 0\n").unwrap();
+    assert_eq!(instrs[0].args().unwrap(), &[1e-3]);
+    assert_eq!(instrs[1].args().unwrap(), &[2.5e-2]);
+}
+
+#[test]
+fn rejects_non_finite_args() {
+    for arg in ["inf", "nan", "-inf", "1e999"] {
+        let err = parse_lines(&format!("X_ERROR({arg}) 0\n")).unwrap_err();
+        assert!(err.contains(arg), "{err}");
+    }
+}
+
+#[test]
+fn handles_brace_layouts() {
+    let a = parse_lines("REPEAT 3{\nH 0\n}\n").unwrap();
+    let b = parse_lines("REPEAT 3\n{\nH 0\n} # done\n").unwrap();
+    let c = parse_lines("REPEAT 3 {\n\tH 0\n}").unwrap();
+    assert_eq!(a, b);
+    assert_eq!(a, c);
+    assert!(matches!(a[0], StimInstr::Repeat { count: 3, .. }));
+}
+
+#[test]
+fn handles_tabs_and_trailing_comments() {
+    let instrs = parse_lines("M\t0 1\t# comment\n").unwrap();
+    assert_eq!(
+        instrs[0].targets().unwrap(),
+        &[StimTarget::Qubit(0), StimTarget::Qubit(1)]
+    );
+}
+
+#[test]
+fn parses_tags() {
+    let instrs = parse_lines("H[my tag] 0\n").unwrap();
+    match &instrs[0] {
+        StimInstr::Op { name, tag, .. } => {
+            assert_eq!(name, "H");
+            assert_eq!(tag.as_deref(), Some("my tag"));
+        }
+        _ => panic!("expected Op"),
+    }
+}
+
+#[test]
+fn reports_failing_token_position() {
+    let err = parse_lines("H 0\nCX 0 foo 1\n").unwrap_err();
+    assert!(err.contains("line 2 col 6"), "{err}");
+    assert!(err.contains("`foo`"), "{err}");
+}
+
+#[test]
+fn rejects_trailing_tokens_after_brace() {
+    assert!(parse_lines("REPEAT 2 {\nH 0\n} H 1\n").is_err());
+    assert!(parse_lines("H 0 {\n}\n").is_err());
+    assert!(parse_lines("REPEAT 2 { H 0\n}\n").is_err());
+    assert!(parse_lines("REPEAT 2 { # loop\nH 0\n}\n").is_ok());
+}
+
+#[test]
+fn rejects_tagged_repeat() {
+    let err = parse_lines("REPEAT[tag] 2 {\nH 0\n}\n").unwrap_err();
+    assert!(err.contains("tag"), "{err}");
+}
@@ -0,0 +1,48 @@
+use std::io::Cursor;
+
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use rstim::executor::Executor;
+use rstim::ir::StimInstr;
+use rstim::parser::{StreamParser, parse_lines};
+
+#[test]
+fn yields_instructions_lazily() {
+    let mut it = StreamParser::new(Cursor::new(
+        "H 0\nREPEAT 2 {\nX 0\nREPEAT 3 {\nM 0\n}\n}\nM 0\n",
+    ));
+    assert_eq!(it.next().unwrap().unwrap().name(), Some("H"));
+    match it.next().unwrap().unwrap() {
+        StimInstr::Repeat { count, body } => {
+            assert_eq!(count, 2);
+            assert_eq!(body.len(), 2);
+            assert!(matches!(body[1], StimInstr::Repeat { count: 3, .. }));
+        }
+        _ => panic!("expected Repeat"),
+    }
+    assert_eq!(it.next().unwrap().unwrap().name(), Some("M"));
+    assert!(it.next().is_none());
+}
+
+#[test]
+fn stops_after_first_error() {
+    let mut it = StreamParser::new(Cursor::new("H 0\n}\nH 1\n"));
+    assert!(it.next().unwrap().is_ok());
+    let err = it.next().unwrap().unwrap_err();
+    assert!(err.contains("line 2"));
+    assert!(it.next().is_none());
+}
+
+#[test]
+fn matches_parse_lines() {
+    let program = "H 0\nCNOT 0 1\nREPEAT 2 {\nM 0 1\n}\n";
+    let streamed: Result<Vec<_>, _> = StreamParser::new(program.as_bytes()).collect();
+    assert_eq!(streamed.unwrap(), parse_lines(program).unwrap());
+}
+
+#[test]
+fn executor_runs_from_reader() {
+    let mut ex = Executor::from_reader(Cursor::new("X 0\nM 0\n")).unwrap();
+    let mut rng = StdRng::seed_from_u64(1);
+    assert_eq!(ex.run(&mut rng).unwrap().measurements, vec![true]);
+}
@@ -0,0 +1,72 @@
+mod clifford_helpers;
+use clifford_helpers::random_gates;
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use rstim::pauli::{Pauli, PauliString};
+use rstim::sim::tableau::StabilizerState;
+
+fn ps(s: &str) -> PauliString {
+    s.parse().unwrap()
+}
+
+#[test]
+fn multiplication_tracks_phase() {
+    assert_eq!((&ps("X") * &ps("Y")).to_string(), "+iZ");
+    assert_eq!((&ps("Y") * &ps("X")).to_string(), "-iZ");
+    assert_eq!((&ps("X") * &ps("Z")).to_string(), "-iY");
+    assert_eq!((&ps("XZ") * &ps("ZX")).to_string(), "+YY");
+    assert_eq!((&ps("-iX") * &ps("iX")).to_string(), "+_");
+    let mut p = ps("X");
+    p *= &ps("_Z_");
+    assert_eq!(p.to_string(), "+XZ_");
+}
+
+#[test]
+fn commutation_weight_and_slicing() {
+    assert!(ps("XX").commutes(&ps("ZZ")));
+    assert!(!ps("X_").commutes(&ps("ZZ")));
+    assert!(!ps("Z").commutes(&ps("XX")));
+    let p = ps("-X_ZY_");
+    assert_eq!(p.weight(), 3);
+    assert_eq!(p.slice(1..4).to_string(), "+_ZY");
+    assert_eq!(
+        p.iter().collect::<Vec<_>>(),
+        vec![Pauli::X, Pauli::I, Pauli::Z, Pauli::Y, Pauli::I]
+    );
+    assert!(ps("+iX").to_string() == "+iX" && !ps("+iX").is_hermitian());
+}
+
+#[test]
+fn long_strings_pack_across_words() {
+    let mut a = PauliString::new(130);
+    a.set(129, Pauli::X);
+    let mut b = PauliString::new(130);
+    b.set(129, Pauli::Z);
+    assert!(!a.commutes(&b));
+    assert_eq!((&a * &b).phase(), 3);
+    assert_eq!((&a * &b).get(129), Pauli::Y);
+}
+
+#[test]
+fn conjugation_matches_the_tableau() {
+    let mut rng = StdRng::seed_from_u64(11);
+    let n = 4;
+    for _ in 0..20 {
+        let mut st = StabilizerState::new(n);
+        let mut rows: Vec<PauliString> = (0..n)
+            .map(|q| {
+                let mut p = PauliString::new(n);
+                p.set(q, Pauli::Z);
+                p
+            })
+            .collect();
+        for gate in random_gates(&mut rng, n, 30) {
+            gate.apply(&mut st);
+            for p in rows.iter_mut() {
+                gate.conjugate(p);
+            }
+        }
+        let rebuilt = StabilizerState::from_stabilizers(&rows).unwrap();
+        assert_eq!(rebuilt.canonical_stabilizers(), st.canonical_stabilizers());
+    }
+}
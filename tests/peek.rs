@@ -0,0 +1,79 @@
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use rstim::pauli::PauliString;
+use rstim::sim::tableau::StabilizerState;
+
+fn expect(st: &StabilizerState, p: &str) -> i8 {
+    st.peek_observable_expectation(&p.parse::<PauliString>().unwrap())
+        .unwrap()
+}
+
+#[test]
+fn single_qubit_peeks() {
+    let mut st = StabilizerState::new(1);
+    assert_eq!((st.peek_x(0), st.peek_y(0), st.peek_z(0)), (0, 0, 1));
+    st.h(0);
+    assert_eq!((st.peek_x(0), st.peek_y(0), st.peek_z(0)), (1, 0, 0));
+    st.s(0);
+    assert_eq!((st.peek_x(0), st.peek_y(0), st.peek_z(0)), (0, 1, 0));
+    st.s(0);
+    assert_eq!(st.peek_bloch(0).to_string(), "-X");
+}
+
+#[test]
+fn peeks_do_not_collapse() {
+    let mut st = StabilizerState::new(1);
+    st.h(0);
+    assert_eq!(st.peek_z(0), 0);
+    assert_eq!(st.peek_x(0), 1);
+    let (_, random) = st.measure_z(0, &mut StdRng::seed_from_u64(1));
+    assert!(random);
+}
+
+#[test]
+fn bell_pair_observables() {
+    let mut st = StabilizerState::new(2);
+    st.h(0);
+    st.cx(0, 1);
+    assert_eq!(expect(&st, "+ZZ"), 1);
+    assert_eq!(expect(&st, "XX"), 1);
+    assert_eq!(expect(&st, "YY"), -1);
+    assert_eq!(expect(&st, "-YY"), 1);
+    assert_eq!(expect(&st, "Z_"), 0);
+    assert_eq!(expect(&st, "Z"), 0);
+    assert_eq!(st.peek_bloch(0).to_string(), "+_");
+}
+
+#[test]
+fn signs_of_mixed_x_and_z_products() {
+    let mut st = StabilizerState::new(2);
+    st.h(0);
+    st.cx(0, 1);
+    st.s(1);
+    st.h(1);
+    assert_eq!(expect(&st, "YZ"), 1);
+    assert_eq!(expect(&st, "XY"), -1);
+    assert_eq!(expect(&st, "ZX"), 1);
+    assert_eq!(expect(&st, "XX"), 0);
+}
+
+#[test]
+fn long_or_non_hermitian_observables_are_rejected() {
+    let st = StabilizerState::new(2);
+    let peek = |p: &str| st.peek_observable_expectation(&p.parse::<PauliString>().unwrap());
+    assert_eq!(
+        peek("ZZZ"),
+        Err("Pauli string on 3 qubits is longer than the 2-qubit state".to_string())
+    );
+    assert_eq!(peek("iZ_"), Err("+iZ_ is not Hermitian".to_string()));
+    assert!(peek("-iXX").is_err());
+}
+
+#[test]
+fn pauli_strings_parse_and_print() {
+    let p: PauliString = "-XZ_YI".parse().unwrap();
+    assert_eq!(p.len(), 5);
+    assert!(p.is_negative());
+    assert_eq!(p.to_string(), "-XZ_Y_");
+    assert!("XQ".parse::<PauliString>().is_err());
+}
@@ -0,0 +1,59 @@
+use rstim::executor::Executor;
+use rstim::parser::parse_lines;
+use rstim::seed::ShotRng;
+use rstim::sim::tableau::StabilizerState;
+
+#[test]
+fn forced_measurements_pick_random_outcomes() {
+    let mut s = StabilizerState::new(2);
+    s.h(0);
+    s.cx(0, 1);
+    assert_eq!(s.measure_z_forced(0, true), Ok(true));
+    assert_eq!(s.peek_z(1), -1);
+    // Now deterministic: forcing agrees without randomness, or fails.
+    assert_eq!(s.measure_z_forced(1, true), Ok(false));
+    assert!(s.measure_z_forced(1, false).is_err());
+    assert_eq!(s.peek_z(1), -1);
+}
+
+#[test]
+fn forced_injection_prepares_the_desired_branch() {
+    // Teleport-style injection: force the ancilla outcome that needs no
+    // correction, leaving |+i> on qubit 1.
+    let mut s = StabilizerState::new(2);
+    s.h(0);
+    s.s(0);
+    s.h(1);
+    s.cz(0, 1);
+    s.h(0);
+    assert_eq!(s.measure_z_forced(0, false), Ok(true));
+    s.h(1);
+    assert_eq!(s.peek_y(1), 1);
+}
+
+#[test]
+fn postselection_keeps_only_zero_shots() {
+    let text = "H 0\nCX 0 1\nM 0 1\nH 2\nM 2\nDETECTOR rec[-1]";
+    let mut ex = Executor::from_instrs(parse_lines(text).unwrap()).unwrap();
+    ex.set_postselection(vec![0], vec![0]).unwrap();
+    let mut kept = 0;
+    for shot in 0..2000 {
+        if let Some(out) = ex.run_postselected(&mut ShotRng::new(50, shot)).unwrap() {
+            assert_eq!(out.measurements, vec![false; 3]);
+            kept += 1;
+        }
+    }
+    assert!((400..600).contains(&kept), "{kept}");
+
+    ex.set_postselection(Vec::new(), Vec::new()).unwrap();
+    let out = ex.run_postselected(&mut ShotRng::new(50, 0)).unwrap();
+    assert!(out.is_some());
+}
+
+#[test]
+fn postselection_indices_are_checked() {
+    let mut ex = Executor::from_instrs(parse_lines("M 0\nDETECTOR rec[-1]").unwrap()).unwrap();
+    assert!(ex.set_postselection(vec![1], vec![]).is_err());
+    assert!(ex.set_postselection(vec![], vec![1]).is_err());
+    assert!(ex.set_postselection(vec![0], vec![0]).is_ok());
+}
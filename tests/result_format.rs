@@ -0,0 +1,78 @@
+use rstim::result_format::{RecordLayout, ResultFormat, read_shots, write_shots};
+
+fn bits(s: &str) -> Vec<bool> {
+    s.chars().map(|c| c == '1').collect()
+}
+
+const ALL_FORMATS: [&str; 6] = ["01", "b8", "r8", "hits", "dets", "ptb64"];
+
+#[test]
+fn parses_format_names() {
+    for name in ALL_FORMATS {
+        assert!(name.parse::<ResultFormat>().is_ok());
+    }
+    assert!("csv".parse::<ResultFormat>().is_err());
+}
+
+#[test]
+fn encodes_known_values() {
+    let shots = vec![bits("0010000001"), bits("0000000000")];
+    let layout = RecordLayout::measurements(10);
+    let enc = |f: &str| write_shots(&shots, f.parse().unwrap(), layout).unwrap();
+    assert_eq!(enc("01"), b"0010000001\n0000000000\n");
+    assert_eq!(enc("b8"), vec![0x04, 0x02, 0x00, 0x00]);
+    assert_eq!(enc("r8"), vec![2, 6, 0, 10]);
+    assert_eq!(enc("hits"), b"2,9\n\n");
+    assert_eq!(enc("dets"), b"shot M2 M9\nshot\n");
+}
+
+#[test]
+fn r8_splits_long_runs() {
+    let mut shot = vec![false; 300];
+    shot[299] = true;
+    let layout = RecordLayout::measurements(300);
+    let data = write_shots(std::slice::from_ref(&shot), ResultFormat::R8, layout).unwrap();
+    assert_eq!(data, vec![255, 44, 0]);
+    assert_eq!(
+        read_shots(&data, ResultFormat::R8, layout).unwrap(),
+        vec![shot]
+    );
+}
+
+#[test]
+fn dets_uses_section_prefixes() {
+    let layout = RecordLayout::detectors(3, 2);
+    let shot = bits("01101");
+    let data = write_shots(std::slice::from_ref(&shot), ResultFormat::Dets, layout).unwrap();
+    assert_eq!(data, b"shot D1 D2 L1\n");
+    assert_eq!(
+        read_shots(&data, ResultFormat::Dets, layout).unwrap(),
+        vec![shot]
+    );
+}
+
+#[test]
+fn round_trips_every_format() {
+    let layout = RecordLayout::detectors(7, 3);
+    let shots: Vec<Vec<bool>> = (0..128u32)
+        .map(|s| (0..10).map(|b| (s * 7 + b * 3) % 5 == 0).collect())
+        .collect();
+    for name in ALL_FORMATS {
+        let format: ResultFormat = name.parse().unwrap();
+        let data = write_shots(&shots, format, layout).unwrap();
+        assert_eq!(read_shots(&data, format, layout).unwrap(), shots, "{name}");
+    }
+}
+
+#[test]
+fn ptb64_requires_full_groups() {
+    let layout = RecordLayout::measurements(2);
+    assert!(write_shots(&[bits("01")], ResultFormat::Ptb64, layout).is_err());
+}
+
+#[test]
+fn rejects_wrong_shot_width() {
+    let layout = RecordLayout::measurements(3);
+    assert!(write_shots(&[bits("01")], ResultFormat::Format01, layout).is_err());
+    assert!(read_shots(b"01\n", ResultFormat::Format01, layout).is_err());
+}
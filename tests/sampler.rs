@@ -0,0 +1,69 @@
+use rstim::circuit::Circuit;
+use rstim::sampler::{BitTable, CHUNK_SHOTS, Sampler};
+
+const NOISY: &str = "H 0\nCX 0 1\nX_ERROR(0.2) 0 1\nM 0 1 2\n";
+
+#[test]
+fn bit_table_round_trips_bits() {
+    let mut table = BitTable::new(2, 70);
+    table.set(1, 0, true);
+    table.set(1, 69, true);
+    table.set(1, 0, false);
+    assert!(table.get(1, 69));
+    assert!(!table.get(0, 69));
+    assert_eq!(table.row(1).iter().filter(|&&b| b).count(), 1);
+}
+
+#[test]
+fn samples_deterministic_records() {
+    let circuit = Circuit::parse("X 1\nM 0 1\n").unwrap();
+    let table = Sampler::from_circuit(&circuit, 0)
+        .unwrap()
+        .sample(5)
+        .unwrap();
+    assert_eq!(table.num_rows(), 5);
+    assert_eq!(table.num_cols(), 2);
+    for shot in 0..5 {
+        assert_eq!(table.row(shot), vec![false, true]);
+    }
+}
+
+#[test]
+fn thread_count_does_not_change_results() {
+    let circuit = Circuit::parse(NOISY).unwrap();
+    let shots = 3 * CHUNK_SHOTS + 17;
+    let single = Sampler::from_circuit(&circuit, 42)
+        .unwrap()
+        .sample(shots)
+        .unwrap();
+    for threads in [2, 3, 8] {
+        let mut sampler = Sampler::from_circuit(&circuit, 42).unwrap();
+        sampler.set_threads(threads);
+        assert_eq!(sampler.sample(shots).unwrap(), single);
+    }
+    let other = Sampler::from_circuit(&circuit, 43)
+        .unwrap()
+        .sample(shots)
+        .unwrap();
+    assert_ne!(other, single);
+}
+
+#[test]
+fn detects_flips_relative_to_the_reference() {
+    let program = "X 0\nX_ERROR(1) 1\nM 0 1\nDETECTOR rec[-2]\nDETECTOR rec[-1]\nOBSERVABLE_INCLUDE(2) rec[-1]\n";
+    let sampler = Sampler::from_circuit(&Circuit::parse(program).unwrap(), 0).unwrap();
+    let (detectors, observables) = sampler.detect(3).unwrap();
+    assert_eq!(observables.num_cols(), 3);
+    for shot in 0..3 {
+        assert_eq!(detectors.row(shot), vec![false, true]);
+        assert_eq!(observables.row(shot), vec![false, false, true]);
+    }
+
+    let noisy = Circuit::parse("X_ERROR(0.3) 0\nM 0\nDETECTOR rec[-1]\n").unwrap();
+    let sampler = Sampler::from_circuit(&noisy, 7).unwrap();
+    let (all, _) = sampler.detect(CHUNK_SHOTS + 40).unwrap();
+    let (part, _) = sampler.detect_range(CHUNK_SHOTS as u64 - 10, 30).unwrap();
+    for i in 0..30 {
+        assert_eq!(part.row(i), all.row(CHUNK_SHOTS - 10 + i));
+    }
+}
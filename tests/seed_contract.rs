@@ -0,0 +1,95 @@
+use rand::RngCore;
+use rstim::parser::parse_lines;
+use rstim::sampler::Sampler;
+use rstim::seed::{ShotRng, hits};
+
+const SEED: u64 = 2026;
+
+fn pinned(program: &str) -> String {
+    let table = Sampler::new(parse_lines(program).unwrap(), SEED)
+        .unwrap()
+        .sample(8)
+        .unwrap();
+    let rows: Vec<String> = (0..8)
+        .map(|i| {
+            table
+                .row(i)
+                .iter()
+                .map(|&b| if b { '1' } else { '0' })
+                .collect()
+        })
+        .collect();
+    rows.join(" ")
+}
+
+#[test]
+fn generator_is_fixed() {
+    let mut rng = ShotRng::new(7, 3);
+    assert_eq!(rng.next_u64(), 0xe2446aac83b17733);
+    assert_eq!(rng.next_u64(), 0x2a11f39274de89b8);
+}
+
+#[test]
+fn random_measurements() {
+    assert_eq!(pinned("H 0\nM 0\n"), "1 0 1 0 0 0 0 0");
+    assert_eq!(
+        pinned("H 0\nMX 0\nMY 0\nM 0\n"),
+        "001 000 000 000 011 010 000 000"
+    );
+}
+
+#[test]
+fn pauli_errors() {
+    assert_eq!(
+        pinned("X_ERROR(0.5) 0 1 2\nM 0 1 2\n"),
+        "000 010 101 111 101 010 011 110"
+    );
+    assert_eq!(
+        pinned("H 0 1\nZ_ERROR(0.5) 0 1\nH 0 1\nM 0 1\n"),
+        "01 01 00 00 11 00 11 10"
+    );
+}
+
+#[test]
+fn depolarizing_errors() {
+    assert_eq!(
+        pinned("DEPOLARIZE1(0.75) 0 1\nM 0 1\n"),
+        "00 00 11 11 10 10 01 10"
+    );
+    assert_eq!(
+        pinned("DEPOLARIZE2(0.9) 0 1\nM 0 1\n"),
+        "00 00 11 01 10 00 11 01"
+    );
+}
+
+#[test]
+fn zero_probability_noise_draws_nothing() {
+    assert_eq!(pinned("X_ERROR(0) 0\nH 1\nM 0 1\n"), pinned("H 1\nM 0 1\n"));
+}
+
+#[test]
+fn hits_skip_to_firing_trials() {
+    let mut rng = ShotRng::new(SEED, 0);
+    let n = 1_000_000;
+    let found = hits(&mut rng, 1e-3, n);
+    assert!(found.windows(2).all(|w| w[0] < w[1]));
+    assert!(found.last().is_some_and(|&i| i < n));
+    let sigma = (n as f64 * 1e-3).sqrt();
+    assert!((found.len() as f64 - 1000.0).abs() < 5.0 * sigma);
+    assert_eq!(hits(&mut rng, 1.0, 4), vec![0, 1, 2, 3]);
+}
+
+#[test]
+fn shots_do_not_depend_on_batching() {
+    let mut sampler = Sampler::new(
+        parse_lines("DEPOLARIZE1(0.3) 0 1\nH 2\nM 0 1 2\n").unwrap(),
+        SEED,
+    )
+    .unwrap();
+    let all = sampler.sample(600).unwrap();
+    sampler.set_threads(3);
+    let part = sampler.sample_range(250, 300).unwrap();
+    for i in 0..300 {
+        assert_eq!(part.row(i), all.row(250 + i));
+    }
+}
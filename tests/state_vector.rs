@@ -0,0 +1,139 @@
+mod clifford_helpers;
+use clifford_helpers::{Gate, random_gates};
+use num_complex::Complex64;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rstim::sim::tableau::StabilizerState;
+
+const R: f64 = std::f64::consts::FRAC_1_SQRT_2;
+
+fn c(re: f64, im: f64) -> Complex64 {
+    Complex64::new(re, im)
+}
+
+fn close(a: &[Complex64], b: &[Complex64]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).all(|(x, y)| (x - y).norm() < 1e-9)
+}
+
+/// Dense reference: applies the same gate to plain amplitudes.
+fn dense_gate(amps: &mut [Complex64], gate: Gate) {
+    let set = |j: usize, q: usize| j >> q & 1 == 1;
+    let mut phase = |q: usize, f: Complex64| {
+        for (j, a) in amps.iter_mut().enumerate() {
+            if set(j, q) {
+                *a *= f;
+            }
+        }
+    };
+    match gate {
+        Gate::S(a) => phase(a, c(0.0, 1.0)),
+        Gate::SDag(a) => phase(a, c(0.0, -1.0)),
+        Gate::Z(a) => phase(a, c(-1.0, 0.0)),
+        Gate::Cz(a, b) => {
+            for (j, x) in amps.iter_mut().enumerate() {
+                if set(j, a) && set(j, b) {
+                    *x = -*x;
+                }
+            }
+        }
+        Gate::H(a) | Gate::X(a) | Gate::Y(a) => {
+            for j in (0..amps.len()).filter(|&j| !set(j, a)) {
+                let (u, v) = (amps[j], amps[j | 1 << a]);
+                (amps[j], amps[j | 1 << a]) = match gate {
+                    Gate::H(_) => ((u + v) * R, (u - v) * R),
+                    Gate::X(_) => (v, u),
+                    _ => (v * c(0.0, -1.0), u * c(0.0, 1.0)),
+                };
+            }
+        }
+        Gate::Cx(ctl, t) => {
+            for j in (0..amps.len()).filter(|&j| set(j, ctl) && !set(j, t)) {
+                amps.swap(j, j | 1 << t);
+            }
+        }
+    }
+}
+
+#[test]
+fn small_states_have_expected_amplitudes() {
+    let zero = StabilizerState::new(2);
+    assert!(close(
+        &zero.to_state_vector().unwrap(),
+        &[c(1.0, 0.0), c(0.0, 0.0), c(0.0, 0.0), c(0.0, 0.0)]
+    ));
+
+    let mut bell = StabilizerState::new(2);
+    bell.h(0);
+    bell.cx(0, 1);
+    assert!(close(
+        &bell.to_state_vector().unwrap(),
+        &[c(R, 0.0), c(0.0, 0.0), c(0.0, 0.0), c(R, 0.0)]
+    ));
+
+    let mut plus_i = StabilizerState::new(1);
+    plus_i.h(0);
+    plus_i.s(0);
+    assert!(close(
+        &plus_i.to_state_vector().unwrap(),
+        &[c(R, 0.0), c(0.0, R)]
+    ));
+
+    let mut one = StabilizerState::new(1);
+    one.x_gate(0);
+    assert!(close(
+        &one.to_state_vector().unwrap(),
+        &[c(0.0, 0.0), c(1.0, 0.0)]
+    ));
+}
+
+#[test]
+fn random_circuits_match_dense_simulation_and_round_trip() {
+    let mut rng = StdRng::seed_from_u64(45);
+    for _ in 0..200 {
+        let n = rng.gen_range(1..6);
+        let mut state = StabilizerState::new(n);
+        let mut amps = vec![c(0.0, 0.0); 1 << n];
+        amps[0] = c(1.0, 0.0);
+        for gate in random_gates(&mut rng, n, 30) {
+            gate.apply(&mut state);
+            dense_gate(&mut amps, gate);
+        }
+
+        let got = state.to_state_vector().unwrap();
+        let overlap: Complex64 = got.iter().zip(&amps).map(|(x, y)| x.conj() * y).sum();
+        assert!((overlap.norm() - 1.0).abs() < 1e-9);
+
+        // Any global phase and norm are accepted.
+        let scaled: Vec<Complex64> = amps.iter().map(|a| a * c(0.0, -3.0)).collect();
+        let back = StabilizerState::from_state_vector(&scaled).unwrap();
+        assert_eq!(back.canonical_stabilizers(), state.canonical_stabilizers());
+        assert!(close(&back.to_state_vector().unwrap(), &got));
+    }
+}
+
+#[test]
+fn non_stabilizer_states_are_rejected() {
+    let t_state = [c(R, 0.0), c(0.5, 0.5)];
+    assert!(StabilizerState::from_state_vector(&t_state).is_err());
+    let uneven = [c(0.3f64.sqrt(), 0.0), c(0.7f64.sqrt(), 0.0)];
+    assert!(StabilizerState::from_state_vector(&uneven).is_err());
+    let third = 1.0 / 3f64.sqrt();
+    let mut w = vec![c(0.0, 0.0); 8];
+    for j in [1, 2, 4] {
+        w[j] = c(third, 0.0);
+    }
+    assert!(StabilizerState::from_state_vector(&w).is_err());
+    // Equal magnitudes on a subspace but a cubic phase (CCZ on |+++>).
+    let mut ccz = vec![c(0.5 * R, 0.0); 8];
+    ccz[7] = -ccz[7];
+    assert!(StabilizerState::from_state_vector(&ccz).is_err());
+    assert!(StabilizerState::from_state_vector(&[c(1.0, 0.0); 3]).is_err());
+    assert!(StabilizerState::from_state_vector(&[c(0.0, 0.0); 4]).is_err());
+    assert!(StabilizerState::from_state_vector(&[]).is_err());
+}
+
+#[test]
+fn large_states_are_refused() {
+    let err = StabilizerState::new(25).to_state_vector().unwrap_err();
+    assert!(err.contains("25 qubits"), "{err}");
+}
@@ -0,0 +1,34 @@
+use rstim::parser::parse_lines;
+use rstim::stats::stats;
+
+#[test]
+fn counts_basic_circuit() {
+    let program =
+        "H 0\nCX 0 1\nM 0 !1\nDETECTOR rec[-1] rec[-2]\nOBSERVABLE_INCLUDE(2) rec[-1]\nTICK\n";
+    let s = stats(&parse_lines(program).unwrap());
+    assert_eq!(s.num_qubits, 2);
+    assert_eq!(s.num_measurements, 2);
+    assert_eq!(s.num_detectors, 1);
+    assert_eq!(s.num_observables, 3);
+    assert_eq!(s.num_ticks, 1);
+    assert_eq!(s.max_lookback, 2);
+    assert_eq!(s.gate_counts["CX"], 1);
+}
+
+#[test]
+fn scales_repeat_without_unrolling() {
+    let program = "REPEAT 1000000000000 {\nM 4\nDETECTOR rec[-3]\nTICK\n}\n";
+    let s = stats(&parse_lines(program).unwrap());
+    assert_eq!(s.num_qubits, 5);
+    assert_eq!(s.num_measurements, 1_000_000_000_000);
+    assert_eq!(s.num_detectors, 1_000_000_000_000);
+    assert_eq!(s.num_ticks, 1_000_000_000_000);
+    assert_eq!(s.max_lookback, 3);
+    assert_eq!(s.gate_counts["M"], 1_000_000_000_000);
+}
+
+#[test]
+fn counts_sweep_bits() {
+    let s = stats(&parse_lines("CX sweep[3] 0\n").unwrap());
+    assert_eq!(s.num_sweep_bits, 4);
+}
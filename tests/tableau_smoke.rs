@@ -12,3 +12,27 @@ fn h_then_m_gives_random() {
     assert!(m0 == 0 || m0 == 1);
     assert!(m1 == 0 || m1 == 1);
 }
+
+#[test]
+fn deterministic_outcome_sign_after_s_on_bell_pair() {
+    // The leading CXs act trivially on |0000> but mix the destabilizers, so
+    // the sign of the last measurement is built from an X*Z row product.
+    for seed in 0..32 {
+        let mut st = StabilizerState::new(4);
+        st.cx(1, 3);
+        st.cx(3, 2);
+        st.cx(3, 0);
+        st.h(1);
+        st.h(3);
+        st.cx(3, 0);
+        st.s(0);
+        st.cx(1, 3);
+        let mut rng = StdRng::seed_from_u64(seed);
+        let (m0, _) = st.measure_z(0, &mut rng);
+        let (m1, _) = st.measure_z(1, &mut rng);
+        let (m2, _) = st.measure_z(2, &mut rng);
+        let (m3, random) = st.measure_z(3, &mut rng);
+        assert!(!random);
+        assert_eq!((m2, m3), (0, m0 ^ m1), "seed {seed}");
+    }
+}
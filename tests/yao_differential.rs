@@ -0,0 +1,121 @@
+mod clifford_helpers;
+use std::collections::HashMap;
+
+use clifford_helpers::random_gate;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rstim::executor::Executor;
+use rstim::parser::parse_lines;
+use rstim::seed::ShotRng;
+use rstim::yao_backend::{YaoExecutor, YaoState};
+
+const SHOTS: u64 = 4000;
+
+type Histogram = HashMap<Vec<bool>, f64>;
+
+fn tableau_histogram(text: &str) -> Histogram {
+    let mut ex = Executor::from_instrs(parse_lines(text).unwrap()).unwrap();
+    histogram(|shot| ex.run(&mut ShotRng::new(1, shot)).unwrap().measurements)
+}
+
+fn yao_histogram(text: &str) -> Histogram {
+    let mut ex = YaoExecutor::compile(parse_lines(text).unwrap()).unwrap();
+    histogram(|shot| ex.run(&mut ShotRng::new(2, shot)).unwrap().measurements)
+}
+
+fn histogram(mut run: impl FnMut(u64) -> Vec<bool>) -> Histogram {
+    let mut out = HashMap::new();
+    for shot in 0..SHOTS {
+        *out.entry(run(shot)).or_insert(0.0) += 1.0 / SHOTS as f64;
+    }
+    out
+}
+
+fn total_variation(a: &Histogram, b: &Histogram) -> f64 {
+    let mut keys: Vec<&Vec<bool>> = a.keys().chain(b.keys()).collect();
+    keys.sort();
+    keys.dedup();
+    let d: f64 = keys
+        .iter()
+        .map(|k| (a.get(*k).unwrap_or(&0.0) - b.get(*k).unwrap_or(&0.0)).abs())
+        .sum();
+    d / 2.0
+}
+
+fn assert_same_distribution(text: &str) {
+    let tv = total_variation(&tableau_histogram(text), &yao_histogram(text));
+    assert!(tv < 0.08, "distance {tv} for circuit:\n{text}");
+}
+
+fn random_circuit(rng: &mut StdRng) -> String {
+    let n = rng.gen_range(1..5);
+    let mut text = String::new();
+    let mut measurements = 0;
+    while measurements < 5 {
+        let a = rng.gen_range(0..n);
+        let line = match rng.gen_range(0..12) {
+            0..=5 => random_gate(rng, n).to_string(),
+            6 => format!("R{} {a}", ["", "X", "Y"][rng.gen_range(0..3)]),
+            7 => format!("DEPOLARIZE1(0.1) {a}"),
+            _ => {
+                measurements += 1;
+                format!("M{} {a}", ["", "X", "Y"][rng.gen_range(0..3)])
+            }
+        };
+        text.push_str(&line);
+        text.push('\n');
+    }
+    text
+}
+
+#[test]
+fn fixed_circuits_agree() {
+    for text in [
+        "H 0\nCX 0 1\nM 0 1",
+        "H 0\nCX 0 1 1 2\nMX 0 1 2",
+        "H 0\nS 0\nMY 0\nMX 0",
+        "H 0\nM 0\nR 0\nM 0",
+        "RX 0\nMX 0\nRY 1\nMY 1",
+        "X_ERROR(0.2) 0\nZ_ERROR(0.3) 1\nH 1\nM 0 1",
+        "H 0\nCX 0 1\nDEPOLARIZE2(0.3) 0 1\nM 0 1",
+        "REPEAT 3 {\n    H 0\n    M 0\n}",
+    ] {
+        assert_same_distribution(text);
+    }
+}
+
+#[test]
+fn random_circuits_agree() {
+    let mut rng = StdRng::seed_from_u64(46);
+    for _ in 0..30 {
+        assert_same_distribution(&random_circuit(&mut rng));
+    }
+}
+
+#[test]
+fn resets_are_deterministic() {
+    let text = "H 0 1\nCX 0 1\nR 0\nRX 1\nM 0\nMX 1\nRY 0\nMY 0";
+    let mut ex = YaoExecutor::compile(parse_lines(text).unwrap()).unwrap();
+    for shot in 0..20 {
+        let out = ex.run(&mut ShotRng::new(0, shot)).unwrap();
+        assert_eq!(out.measurements, vec![false, false, false]);
+    }
+}
+
+#[test]
+fn large_registers_are_rejected() {
+    assert!(YaoExecutor::compile(parse_lines("H 40").unwrap()).is_err());
+}
+
+#[test]
+fn repeated_pair_targets_are_rejected() {
+    assert!(YaoExecutor::compile(parse_lines("CX 0 0").unwrap()).is_err());
+    assert!(YaoExecutor::compile(parse_lines("CZ 1 1").unwrap()).is_err());
+}
+
+#[test]
+fn states_do_not_grow() {
+    let mut ex = YaoExecutor::compile(parse_lines("H 0\nCX 0 2").unwrap()).unwrap();
+    let mut state = YaoState::new(2);
+    assert!(ex.run_on(&mut state, &mut ShotRng::new(0, 0)).is_err());
+}